@@ -0,0 +1,470 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sl_utils::utils::http;
+
+use crate::{config::config::Config, ACCOUNTS_PATH, AUTHLIB_INJECTOR_PATH};
+
+use super::{
+    ensure_online,
+    secrets::{self, SecretBackend},
+    yggdrasil::{YggdrasilAccount, YGGDRASIL_SECRET_PREFIX},
+    AuthFlow, AuthServiceError, CancellationToken, LoginEvent, LoginMethod, PlayerProfile,
+};
+
+/// Seconds before the real expiry at which a Minecraft token is treated as stale,
+/// so it doesn't run out in the middle of a launch.
+const EXPIRY_MARGIN: i64 = 5 * 60;
+
+/// Prefix of the secret backend keys holding Microsoft tokens.
+const MICROSOFT_SECRET_PREFIX: &str = "microsoft:";
+
+/// Tokens never end up in `accounts.json`, they're kept in the secret backend instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicrosoftAccount {
+    pub uuid: String,
+    pub username: String,
+    /// Xbox user id, taken from the Minecraft access token
+    #[serde(default)]
+    pub xuid: Option<String>,
+    #[serde(skip)]
+    pub refresh_token: String,
+    #[serde(skip)]
+    pub access_token: String,
+    /// Unix timestamp (in seconds) at which `access_token` expires
+    pub expires_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MicrosoftTokens {
+    refresh_token: String,
+    access_token: String,
+}
+
+impl MicrosoftAccount {
+    /// Runs the Xbox Live -> XSTS -> Minecraft chain on an `AuthFlow` that
+    /// already holds a Microsoft token.
+    async fn from_flow(
+        auth: &mut AuthFlow,
+        refresh_token: String,
+    ) -> Result<Self, AuthServiceError> {
+        auth.login_in_xbox_live().await?;
+        let minecraft = auth.login_in_minecraft().await?;
+
+        let access_token = minecraft.access_token.clone();
+        let expires_at = Utc::now().timestamp() + i64::from(minecraft.expires_in);
+
+        let profile = PlayerProfile::with_endpoints(access_token.clone(), auth.endpoints()).await?;
+
+        Ok(Self {
+            uuid: profile.uuid,
+            username: profile.username,
+            xuid: xuid_from_token(&access_token),
+            refresh_token,
+            access_token,
+            expires_at,
+        })
+    }
+
+    /// Logs in through `method`, `on_event` is first called with the code the user has
+    /// to enter or the page they have to open in their browser, then with the progress of the login.
+    pub async fn login(
+        client_id: &str,
+        method: LoginMethod,
+        cancel: &CancellationToken,
+        mut on_event: impl FnMut(LoginEvent),
+    ) -> Result<Self, AuthServiceError> {
+        ensure_online()?;
+        let mut auth = AuthFlow::new(client_id);
+
+        let token = match method {
+            LoginMethod::DeviceCode => {
+                on_event(LoginEvent::Code(auth.request_code().await?.clone()));
+                auth.wait_for_login_with(cancel, on_event).await?
+            }
+            LoginMethod::Browser => auth.browser_login_with(cancel, on_event).await?,
+        };
+
+        let refresh_token = token.refresh_token.clone();
+        Self::from_flow(&mut auth, refresh_token).await
+    }
+
+    /// Silently logs in again using the stored refresh token.
+    pub async fn refresh(&mut self, client_id: &str) -> Result<(), AuthServiceError> {
+        ensure_online()?;
+        let mut auth = AuthFlow::new(client_id);
+
+        let refresh_token = auth
+            .refresh_login(&self.refresh_token)
+            .await?
+            .refresh_token
+            .clone();
+
+        *self = Self::from_flow(&mut auth, refresh_token).await?;
+        Ok(())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp() + EXPIRY_MARGIN >= self.expires_at
+    }
+
+    fn secret_key(&self) -> String {
+        format!("{MICROSOFT_SECRET_PREFIX}{}", self.uuid)
+    }
+
+    fn load_tokens(&mut self, secrets: &dyn SecretBackend) -> Result<(), AuthServiceError> {
+        if let Some(secret) = secrets.get(&self.secret_key())? {
+            let tokens: MicrosoftTokens = serde_json::from_str(&secret)?;
+            self.refresh_token = tokens.refresh_token;
+            self.access_token = tokens.access_token;
+        }
+        Ok(())
+    }
+
+    fn store_tokens(&self, secrets: &dyn SecretBackend) -> Result<(), AuthServiceError> {
+        let tokens = MicrosoftTokens {
+            refresh_token: self.refresh_token.clone(),
+            access_token: self.access_token.clone(),
+        };
+        secrets.set(&self.secret_key(), &serde_json::to_string(&tokens)?)?;
+        Ok(())
+    }
+}
+
+/// Reads the `xuid` claim out of a Minecraft access token (a JWT), without verifying it.
+fn xuid_from_token(access_token: &str) -> Option<String> {
+    let payload = access_token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    let claims: Value = serde_json::from_slice(&payload).ok()?;
+    claims.get("xuid")?.as_str().map(str::to_owned)
+}
+
+#[derive(Deserialize)]
+struct StoredOfflineAccount {
+    username: String,
+}
+
+/// Only the username is stored, the UUID is always derived from it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredOfflineAccount")]
+pub struct OfflineAccount {
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub uuid: String,
+}
+
+impl From<StoredOfflineAccount> for OfflineAccount {
+    fn from(stored: StoredOfflineAccount) -> Self {
+        Self::from_username(stored.username)
+    }
+}
+
+impl OfflineAccount {
+    /// Creates an offline account, validating the username the same way the game does:
+    /// 3 to 16 characters, only ASCII letters, digits and underscores.
+    pub fn new(username: &str) -> Result<Self, AuthServiceError> {
+        validate_username(username)?;
+        Ok(Self::from_username(username.to_owned()))
+    }
+
+    fn from_username(username: String) -> Self {
+        let uuid = offline_uuid(&username);
+        Self { username, uuid }
+    }
+}
+
+/// Checks `username` is 3 to 16 characters, only ASCII letters, digits and underscores.
+pub fn validate_username(username: &str) -> Result<(), AuthServiceError> {
+    let valid_length = (3..=16).contains(&username.len());
+    let valid_chars = username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !valid_length || !valid_chars {
+        return Err(AuthServiceError::InvalidUsername(username.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// The UUID servers in offline mode give a player, the same as Java's
+/// `UUID.nameUUIDFromBytes("OfflinePlayer:<name>")`: an MD5 based version 3 UUID.
+pub fn offline_uuid(username: &str) -> String {
+    let mut hash: [u8; 16] = Md5::digest(format!("OfflinePlayer:{username}")).into();
+    hash[6] = (hash[6] & 0x0f) | 0x30;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+
+    hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Account {
+    Microsoft(MicrosoftAccount),
+    Offline(OfflineAccount),
+    /// An account on a third-party server, launched through authlib-injector
+    Yggdrasil(YggdrasilAccount),
+}
+
+impl Account {
+    /// The key the account is stored and selected by.
+    pub fn id(&self) -> &str {
+        match self {
+            Account::Microsoft(account) => &account.uuid,
+            Account::Offline(account) => &account.uuid,
+            Account::Yggdrasil(account) => &account.uuid,
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        match self {
+            Account::Microsoft(account) => &account.username,
+            Account::Offline(account) => &account.username,
+            Account::Yggdrasil(account) => &account.username,
+        }
+    }
+
+    pub fn uuid(&self) -> &str {
+        match self {
+            Account::Microsoft(account) => &account.uuid,
+            Account::Offline(account) => &account.uuid,
+            Account::Yggdrasil(account) => &account.uuid,
+        }
+    }
+
+    pub fn xuid(&self) -> &str {
+        match self {
+            Account::Microsoft(account) => account.xuid.as_deref().unwrap_or("0"),
+            Account::Offline(_) | Account::Yggdrasil(_) => "0",
+        }
+    }
+
+    /// The `${user_type}` the game expects for this kind of account.
+    pub fn user_type(&self) -> &str {
+        match self {
+            Account::Microsoft(_) => "msa",
+            Account::Offline(_) => "legacy",
+            Account::Yggdrasil(_) => "mojang",
+        }
+    }
+
+    pub fn access_token(&self) -> &str {
+        match self {
+            Account::Microsoft(account) => &account.access_token,
+            Account::Offline(_) => "0",
+            Account::Yggdrasil(account) => &account.access_token,
+        }
+    }
+
+    /// The Minecraft profile with the skins and capes of a Microsoft account.
+    pub async fn profile(&self) -> Result<PlayerProfile, AuthServiceError> {
+        match self {
+            Account::Microsoft(account) => PlayerProfile::new(account.access_token.clone()).await,
+            _ => Err(AuthServiceError::NotMicrosoftAccount),
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.id() == name || self.username().eq_ignore_ascii_case(name)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Accounts {
+    /// Id of the account used when an instance doesn't override it
+    pub active: Option<String>,
+    pub accounts: Vec<Account>,
+}
+
+impl Accounts {
+    /// Seeds the store with the username from the global config, which was the only
+    /// way to pick a player name before accounts existed.
+    fn from_global_config() -> Self {
+        let mut accounts = Self::default();
+
+        if let Some(username) = Config::read_global()
+            .ok()
+            .and_then(|config| config.get("auth_player_name").map(str::to_owned))
+        {
+            accounts.add(Account::Offline(OfflineAccount::from_username(username)));
+        }
+
+        accounts
+    }
+
+    /// Loads the accounts, with their tokens decrypted from the default secret backend.
+    pub fn load() -> Result<Self, AuthServiceError> {
+        Self::load_with(&ACCOUNTS_PATH, &secrets::default_backend()?)
+    }
+
+    /// Loads the accounts at `path`. Fails if the file can't be parsed rather than starting over,
+    /// saving an empty list would delete the tokens of every account.
+    pub fn load_with(path: &Path, secrets: &dyn SecretBackend) -> Result<Self, AuthServiceError> {
+        if !path.exists() {
+            return Ok(Self::from_global_config());
+        }
+
+        let mut accounts: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        for account in &mut accounts.accounts {
+            match account {
+                Account::Microsoft(microsoft) => microsoft.load_tokens(secrets)?,
+                Account::Yggdrasil(yggdrasil) => yggdrasil.load_tokens(secrets)?,
+                Account::Offline(_) => {}
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    /// Saves the accounts, encrypting their tokens with the default secret backend.
+    pub fn overwrite(&self) -> Result<(), AuthServiceError> {
+        self.overwrite_with(&ACCOUNTS_PATH, &secrets::default_backend()?)
+    }
+
+    /// Saves the accounts to `path`, deleting the tokens of the accounts that aren't there anymore.
+    pub fn overwrite_with(
+        &self,
+        path: &Path,
+        secrets: &dyn SecretBackend,
+    ) -> Result<(), AuthServiceError> {
+        let mut stale = secrets.keys()?;
+        stale.retain(|key| {
+            key.starts_with(MICROSOFT_SECRET_PREFIX) || key.starts_with(YGGDRASIL_SECRET_PREFIX)
+        });
+
+        for account in &self.accounts {
+            let key = match account {
+                Account::Microsoft(microsoft) => {
+                    microsoft.store_tokens(secrets)?;
+                    microsoft.secret_key()
+                }
+                Account::Yggdrasil(yggdrasil) => {
+                    yggdrasil.store_tokens(secrets)?;
+                    yggdrasil.secret_key()
+                }
+                Account::Offline(_) => continue,
+            };
+            stale.retain(|stale_key| *stale_key != key);
+        }
+
+        for key in stale {
+            secrets.delete(&key)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Finds an account by its id or username.
+    pub fn find(&self, name: &str) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.matches(name))
+    }
+
+    pub fn active(&self) -> Option<&Account> {
+        self.find(self.active.as_deref()?)
+    }
+
+    /// Adds `account`, replacing any stored account with the same id.
+    /// The first account added becomes the active one.
+    pub fn add(&mut self, account: Account) {
+        if self.active.is_none() {
+            self.active = Some(account.id().to_owned());
+        }
+
+        match self
+            .accounts
+            .iter_mut()
+            .find(|existing| existing.id() == account.id())
+        {
+            Some(existing) => *existing = account,
+            None => self.accounts.push(account),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<Account, AuthServiceError> {
+        let index = self
+            .accounts
+            .iter()
+            .position(|account| account.matches(name))
+            .ok_or_else(|| AuthServiceError::AccountNotFound(name.to_owned()))?;
+
+        let removed = self.accounts.remove(index);
+
+        if self.active.as_deref() == Some(removed.id()) {
+            self.active = self.accounts.first().map(|account| account.id().to_owned());
+        }
+
+        Ok(removed)
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<&Account, AuthServiceError> {
+        let id = self
+            .find(name)
+            .ok_or_else(|| AuthServiceError::AccountNotFound(name.to_owned()))?
+            .id()
+            .to_owned();
+
+        self.active = Some(id);
+        Ok(self.active().unwrap())
+    }
+
+    /// Returns the account named `name` (or the active account) ready to launch with,
+    /// refreshing Microsoft and Yggdrasil tokens when needed.
+    /// Falls back to logging in through `method` if the refresh token was rejected.
+    pub async fn resolve(
+        client_id: &str,
+        name: Option<&str>,
+        method: LoginMethod,
+        cancel: &CancellationToken,
+        on_event: impl FnMut(LoginEvent),
+    ) -> Result<Account, AuthServiceError> {
+        let mut accounts = Self::load()?;
+
+        let account = match name {
+            Some(name) => accounts
+                .find(name)
+                .ok_or_else(|| AuthServiceError::AccountNotFound(name.to_owned()))?,
+            None => accounts.active().ok_or(AuthServiceError::NoActiveAccount)?,
+        };
+
+        let account = match account.clone() {
+            // An expired token still starts the game, just without online features
+            Account::Microsoft(microsoft) if microsoft.is_expired() && !http::is_offline() => {
+                let mut refreshed = microsoft;
+                match refreshed.refresh(client_id).await {
+                    Ok(()) => {}
+                    // Only a rejected refresh token warrants logging in again,
+                    // anything else (e.g. not owning the game) would fail the same way.
+                    Err(AuthServiceError::InvalidAccessToken) => {
+                        refreshed =
+                            MicrosoftAccount::login(client_id, method, cancel, on_event).await?;
+                    }
+                    Err(err) => return Err(err),
+                }
+                Account::Microsoft(refreshed)
+            }
+            // What the last launch left is enough to start the game
+            Account::Yggdrasil(yggdrasil) if http::is_offline() => {
+                if yggdrasil.metadata.is_none() || !AUTHLIB_INJECTOR_PATH.exists() {
+                    return Err(AuthServiceError::Offline);
+                }
+                Account::Yggdrasil(yggdrasil)
+            }
+            Account::Yggdrasil(mut yggdrasil) => {
+                yggdrasil.prepare_launch(cancel).await?;
+                Account::Yggdrasil(yggdrasil)
+            }
+            account => account,
+        };
+
+        accounts.add(account.clone());
+        accounts.overwrite()?;
+
+        Ok(account)
+    }
+}
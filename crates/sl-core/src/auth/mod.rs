@@ -1,11 +1,81 @@
-use reqwest::{
-    header::{HeaderValue, AUTHORIZATION},
-    Client, StatusCode,
-};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use sl_utils::utils::{errors::DownloadError, http};
+use std::{collections::HashMap, time::Duration};
 use thiserror::Error;
+use tokio::time::Instant;
+
+pub use tokio_util::sync::CancellationToken;
+
+pub mod accounts;
+pub mod names;
+mod pkce;
+pub mod secrets;
+pub mod skins;
+pub mod yggdrasil;
+
+/// Azure application id used for every Microsoft login.
+pub const MICROSOFT_CLIENT_ID: &str = "74909cec-49b6-4fee-aa60-1b2a57ef72e1"; // Please don't steal :(
+
+/// Base URLs of every service taking part in the Microsoft login.
+/// Defaults to the real services, tests point them to a local mock server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthEndpoints {
+    /// OAuth2 endpoints, `/devicecode`, `/authorize` and `/token` are appended to it
+    pub microsoft: String,
+    pub xbox_live: String,
+    pub xsts: String,
+    pub minecraft_services: String,
+}
+
+impl Default for AuthEndpoints {
+    fn default() -> Self {
+        Self {
+            microsoft: "https://login.microsoftonline.com/consumers/oauth2/v2.0".to_string(),
+            xbox_live: "https://user.auth.xboxlive.com".to_string(),
+            xsts: "https://xsts.auth.xboxlive.com".to_string(),
+            minecraft_services: "https://api.minecraftservices.com".to_string(),
+        }
+    }
+}
+
+impl AuthEndpoints {
+    /// Points every service to the same base URL.
+    pub fn from_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Self {
+            microsoft: format!("{base}/consumers/oauth2/v2.0"),
+            xbox_live: base.to_string(),
+            xsts: base.to_string(),
+            minecraft_services: base.to_string(),
+        }
+    }
+
+    fn device_code(&self) -> String {
+        format!("{}/devicecode", self.microsoft)
+    }
+
+    fn authorize(&self) -> String {
+        format!("{}/authorize", self.microsoft)
+    }
+
+    fn token(&self) -> String {
+        format!("{}/token", self.microsoft)
+    }
+
+    fn xbox_live_authenticate(&self) -> String {
+        format!("{}/user/authenticate", self.xbox_live)
+    }
+
+    fn xsts_authorize(&self) -> String {
+        format!("{}/xsts/authorize", self.xsts)
+    }
+
+    fn minecraft(&self, path: &str) -> String {
+        format!("{}{path}", self.minecraft_services)
+    }
+}
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AuthCodeResponse {
@@ -17,6 +87,29 @@ pub struct AuthCodeResponse {
     pub message: String,
 }
 
+/// How the user logs in with their Microsoft account.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginMethod {
+    /// Enter a code shown by the launcher on microsoft.com/link
+    #[default]
+    DeviceCode,
+    /// Log in on a page opened in the browser, which redirects back to the launcher
+    Browser,
+}
+
+/// Progress of a Microsoft login, reported while waiting for the user.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LoginEvent {
+    /// The code the user has to enter in their browser
+    Code(AuthCodeResponse),
+    /// The user hasn't entered the code yet, it expires in `remaining_secs`
+    Waiting { remaining_secs: u64 },
+    /// The page the user has to log in on, for the browser login
+    OpenBrowser { url: String },
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AuthTokenResponse {
     pub token_type: String,
@@ -58,6 +151,125 @@ pub enum AuthServiceError {
 
     #[error("{0}")]
     Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Download(#[from] DownloadError),
+
+    #[error("Account {0} was not found.")]
+    AccountNotFound(String),
+
+    #[error("No account is selected.")]
+    NoActiveAccount,
+
+    #[error("{0} is not a valid username, it must be 3 to 16 letters, digits or underscores.")]
+    InvalidUsername(String),
+
+    #[error("{0}")]
+    Secret(#[from] secrets::SecretError),
+
+    #[error("This account does not own Minecraft: Java Edition.")]
+    GameNotOwned,
+
+    #[error(
+        "This account owns Minecraft but has no profile yet, create one at minecraft.net first."
+    )]
+    NoMinecraftProfile,
+
+    #[error("This Microsoft account has no Xbox profile, sign in at xbox.com once to create one.")]
+    NoXboxProfile,
+
+    #[error("Xbox Live is not available in this account's country or region.")]
+    XboxRegionBanned,
+
+    #[error("This account needs adult verification on xbox.com before it can play.")]
+    AdultVerificationRequired,
+
+    #[error(
+        "This is a child account, an adult must add it to a Microsoft family before it can play."
+    )]
+    ChildAccount,
+
+    #[error("Xbox Live authorization failed with error code {0}.")]
+    Xsts(u64),
+
+    #[error("The Xbox or Minecraft services responded with an unexpected status: {0}")]
+    UnexpectedStatus(StatusCode),
+
+    #[error("The login code has expired, please try again.")]
+    DeviceCodeExpired,
+
+    #[error("The login was cancelled.")]
+    Cancelled,
+
+    #[error("The login was not completed in time, please try again.")]
+    LoginTimedOut,
+
+    #[error("The browser was redirected back with an invalid response.")]
+    InvalidRedirect,
+
+    #[error("Invalid username or password.")]
+    InvalidCredentials,
+
+    #[error("The authentication server rejected the request: {0}")]
+    Yggdrasil(String),
+
+    #[error("The downloaded authlib-injector doesn't match its checksum.")]
+    AuthlibInjectorChecksum,
+
+    #[error("Invalid skin: {0}")]
+    InvalidSkin(String),
+
+    #[error("Cape {0} is not owned by this player.")]
+    CapeNotFound(String),
+
+    #[error("Skins, capes and names can only be changed on Microsoft accounts.")]
+    NotMicrosoftAccount,
+
+    #[error("The name {0} is already taken.")]
+    NameTaken(String),
+
+    #[error("The name {0} is not allowed.")]
+    NameNotAllowed(String),
+
+    #[error("The name can't be changed yet, it can only be changed once every 30 days.")]
+    NameChangeNotAllowed,
+
+    #[error("Too many requests, please wait a moment and try again.")]
+    RateLimited,
+
+    #[error("This needs the network, but the launcher is in offline mode.")]
+    Offline,
+}
+
+/// Fails with [`AuthServiceError::Offline`] in offline mode, before anything that can't work without the network.
+pub(crate) fn ensure_online() -> Result<(), AuthServiceError> {
+    if http::is_offline() {
+        return Err(AuthServiceError::Offline);
+    }
+    Ok(())
+}
+
+/// Maps the failed statuses shared by the Xbox and Minecraft services to their errors.
+fn check_status(response: reqwest::Response) -> Result<reqwest::Response, AuthServiceError> {
+    match response.status() {
+        status if status.is_success() => Ok(response),
+        StatusCode::UNAUTHORIZED => Err(AuthServiceError::InvalidAccessToken),
+        StatusCode::TOO_MANY_REQUESTS => Err(AuthServiceError::RateLimited),
+        status => Err(AuthServiceError::UnexpectedStatus(status)),
+    }
+}
+
+/// Like [`check_status`], for the profile endpoints where a 404 means the account has no profile.
+fn check_profile_status(
+    response: reqwest::Response,
+) -> Result<reqwest::Response, AuthServiceError> {
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(AuthServiceError::NoMinecraftProfile);
+    }
+    check_status(response)
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -65,20 +277,55 @@ struct AuthServiceErrorMessage {
     error: String,
 }
 
+/// Body of a failed XSTS authorization.
+#[derive(Debug, Deserialize)]
+struct XstsErrorResponse {
+    #[serde(rename = "XErr")]
+    xerr: u64,
+}
+
+impl From<XstsErrorResponse> for AuthServiceError {
+    fn from(value: XstsErrorResponse) -> Self {
+        match value.xerr {
+            2148916233 => AuthServiceError::NoXboxProfile,
+            2148916235 => AuthServiceError::XboxRegionBanned,
+            2148916236 | 2148916237 => AuthServiceError::AdultVerificationRequired,
+            2148916238 => AuthServiceError::ChildAccount,
+            code => AuthServiceError::Xsts(code),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Entitlement {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntitlementsResponse {
+    items: Vec<Entitlement>,
+}
+
 pub struct AuthFlow {
     auth_code_res: Option<AuthCodeResponse>,
     auth_token_res: Option<AuthTokenResponse>,
     xbox_auth_res: Option<XboxLiveAuthResponse>,
     minecraft_res: Option<MinecraftAuthResponse>,
     client_id: String,
+    endpoints: AuthEndpoints,
 
     client: Client,
 }
 
 impl AuthFlow {
     pub fn new(client_id: &str) -> Self {
+        Self::with_endpoints(client_id, AuthEndpoints::default())
+    }
+
+    pub fn with_endpoints(client_id: &str, endpoints: AuthEndpoints) -> Self {
         Self {
-            client: Client::new(),
+            client: http::client(),
+            endpoints,
 
             auth_code_res: None,
             auth_token_res: None,
@@ -88,12 +335,16 @@ impl AuthFlow {
         }
     }
 
+    pub fn endpoints(&self) -> &AuthEndpoints {
+        &self.endpoints
+    }
+
     pub async fn request_code(&mut self) -> Result<&AuthCodeResponse, AuthServiceError> {
         let client_id = &self.client_id;
 
         let response = self
             .client
-            .get("https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode")
+            .get(self.endpoints.device_code())
             .query(&[
                 ("client_id", client_id),
                 ("scope", &"XboxLive.signin offline_access".to_string()),
@@ -111,26 +362,56 @@ impl AuthFlow {
     }
 
     pub async fn wait_for_login(&mut self) -> Result<&AuthTokenResponse, AuthServiceError> {
+        self.wait_for_login_with(&CancellationToken::new(), |_| {})
+            .await
+    }
+
+    /// Polls until the user enters the code, honouring the `interval` and `expires_in`
+    /// of the code and any `slow_down` request. `on_event` gets the time left every second.
+    /// Stops with `AuthServiceError::Cancelled` as soon as `cancel` is cancelled.
+    pub async fn wait_for_login_with(
+        &mut self,
+        cancel: &CancellationToken,
+        mut on_event: impl FnMut(LoginEvent),
+    ) -> Result<&AuthTokenResponse, AuthServiceError> {
         let auth_code = self.auth_code_res.as_ref().unwrap();
         let client_id = &self.client_id;
 
+        let deadline = Instant::now() + Duration::from_secs(auth_code.expires_in.max(0) as u64);
+        let mut interval = Duration::from_secs(auth_code.interval);
+        let mut next_poll = Instant::now() + interval;
+        // The countdown ticks on its own, polls can be much further apart after a `slow_down`
+        let mut countdown = tokio::time::interval(Duration::from_secs(1));
+
         loop {
-            std::thread::sleep(std::time::Duration::from_secs(auth_code.interval + 1));
+            tokio::select! {
+                _ = cancel.cancelled() => return Err(AuthServiceError::Cancelled),
+                _ = countdown.tick() => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    on_event(LoginEvent::Waiting {
+                        remaining_secs: remaining.as_secs(),
+                    });
+                    continue;
+                }
+                _ = tokio::time::sleep_until(next_poll.min(deadline)) => {}
+            }
+
+            if Instant::now() >= deadline {
+                return Err(AuthServiceError::DeviceCodeExpired);
+            }
 
             let code_resp = self
                 .client
-                .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
+                .post(self.endpoints.token())
                 .form(&[
-                    ("client_id", client_id),
-                    ("scope", &"XboxLive.signin offline_access".to_string()),
-                    (
-                        "grant_type",
-                        &"urn:ietf:params:oauth:grant-type:device_code".to_string(),
-                    ),
+                    ("client_id", client_id.as_str()),
+                    ("scope", "XboxLive.signin offline_access"),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
                     ("device_code", &auth_code.device_code),
                 ])
                 .send()
                 .await?;
+            next_poll = Instant::now() + interval;
 
             match code_resp.status() {
                 StatusCode::BAD_REQUEST => {
@@ -141,11 +422,16 @@ impl AuthFlow {
                             return Err(AuthServiceError::InvalidAccessToken);
                         }
                         "expired_token" => {
-                            return Err(AuthServiceError::InvalidAccessToken);
+                            return Err(AuthServiceError::DeviceCodeExpired);
                         }
                         "invalid_grant" => {
                             return Err(AuthServiceError::InvalidAccessToken);
                         }
+                        "slow_down" => {
+                            interval += Duration::from_secs(5);
+                            next_poll += Duration::from_secs(5);
+                            continue;
+                        }
                         _ => {
                             continue;
                         }
@@ -165,6 +451,40 @@ impl AuthFlow {
         }
     }
 
+    /// Exchanges a previously stored refresh token for a fresh Microsoft token,
+    /// skipping the device code flow entirely.
+    pub async fn refresh_login(
+        &mut self,
+        refresh_token: &str,
+    ) -> Result<&AuthTokenResponse, AuthServiceError> {
+        let client_id = &self.client_id;
+
+        let response = self
+            .client
+            .post(self.endpoints.token())
+            .form(&[
+                ("client_id", client_id.as_str()),
+                ("scope", "XboxLive.signin offline_access"),
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ])
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let bytes = response.bytes().await?;
+                let response: AuthTokenResponse = serde_json::from_slice(&bytes)?;
+                self.auth_token_res = Some(response);
+                Ok(self.auth_token_res.as_ref().unwrap())
+            }
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED => {
+                Err(AuthServiceError::InvalidAccessToken)
+            }
+            _ => Err(AuthServiceError::UnknownError),
+        }
+    }
+
     pub async fn login_in_xbox_live(&mut self) -> Result<&XboxLiveAuthResponse, AuthServiceError> {
         let auth_token = self.auth_token_res.as_ref().unwrap();
 
@@ -178,14 +498,13 @@ impl AuthFlow {
             "TokenType": "JWT"
         });
 
-        let xbox_res: XboxLiveAuthResponse = self
+        let response = self
             .client
-            .post("https://user.auth.xboxlive.com/user/authenticate")
+            .post(self.endpoints.xbox_live_authenticate())
             .json(&xbox_authenticate_json)
             .send()
-            .await?
-            .json()
             .await?;
+        let xbox_res: XboxLiveAuthResponse = check_status(response)?.json().await?;
 
         self.xbox_auth_res = Some(xbox_res);
         return Ok(self.xbox_auth_res.as_ref().unwrap());
@@ -196,9 +515,9 @@ impl AuthFlow {
         let xbox_token = &xbox_res.token;
         let user_hash = &xbox_res.display_claims["xui"][0]["uhs"];
 
-        let xbox_security_token_res: XboxLiveAuthResponse = self
+        let xsts_resp = self
             .client
-            .post("https://xsts.auth.xboxlive.com/xsts/authorize")
+            .post(self.endpoints.xsts_authorize())
             .json(&json!({
                 "Properties": {
                     "SandboxId": "RETAIL",
@@ -208,15 +527,20 @@ impl AuthFlow {
                 "TokenType": "JWT"
             }))
             .send()
-            .await?
-            .json()
             .await?;
 
+        if xsts_resp.status() == StatusCode::UNAUTHORIZED {
+            let error: XstsErrorResponse = xsts_resp.json().await?;
+            return Err(error.into());
+        }
+
+        let xbox_security_token_res: XboxLiveAuthResponse = check_status(xsts_resp)?.json().await?;
+
         let xbox_security_token = &xbox_security_token_res.token;
 
-        let minecraft_resp: MinecraftAuthResponse = self
+        let response = self
             .client
-            .post("https://api.minecraftservices.com/authentication/login_with_xbox")
+            .post(self.endpoints.minecraft("/authentication/login_with_xbox"))
             .json(&json!({
                 "identityToken":
                     format!(
@@ -226,13 +550,37 @@ impl AuthFlow {
                     )
             }))
             .send()
-            .await?
-            .json()
+            .await?;
+        let minecraft_resp: MinecraftAuthResponse = check_status(response)?.json().await?;
+
+        self.check_entitlements(&minecraft_resp.access_token)
             .await?;
 
         self.minecraft_res = Some(minecraft_resp);
         return Ok(self.minecraft_res.as_ref().unwrap());
     }
+
+    /// Makes sure the account behind `access_token` owns the game.
+    async fn check_entitlements(&self, access_token: &str) -> Result<(), AuthServiceError> {
+        let response = self
+            .client
+            .get(self.endpoints.minecraft("/entitlements/mcstore"))
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+        let response: EntitlementsResponse = check_status(response)?.json().await?;
+
+        let owns_game = response
+            .items
+            .iter()
+            .any(|item| item.name == "product_minecraft" || item.name == "game_minecraft");
+
+        if !owns_game {
+            return Err(AuthServiceError::GameNotOwned);
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -243,26 +591,45 @@ pub struct PlayerProfile {
     pub username: String,
     #[serde(rename = "id")]
     pub uuid: String,
+    #[serde(default)]
+    pub skins: Vec<skins::Skin>,
+    #[serde(default)]
+    pub capes: Vec<skins::Cape>,
+    #[serde(skip)]
+    endpoints: AuthEndpoints,
 }
 
 impl PlayerProfile {
-    pub async fn new(access_token: String) -> Result<PlayerProfile, Box<dyn std::error::Error>> {
-        let client = Client::new();
+    pub async fn new(access_token: String) -> Result<PlayerProfile, AuthServiceError> {
+        Self::with_endpoints(access_token, &AuthEndpoints::default()).await
+    }
+
+    pub async fn with_endpoints(
+        access_token: String,
+        endpoints: &AuthEndpoints,
+    ) -> Result<PlayerProfile, AuthServiceError> {
+        ensure_online()?;
+
+        let client = http::client();
         let response = client
-            .get("https://api.minecraftservices.com/minecraft/profile")
-            .header(
-                AUTHORIZATION,
-                HeaderValue::from_str(&format!("Bearer {}", access_token))?,
-            )
+            .get(endpoints.minecraft("/minecraft/profile"))
+            .bearer_auth(&access_token)
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            return Err(format!("API error: {}", response.status()).into());
-        }
+        Self::from_response(response, access_token, endpoints.clone()).await
+    }
 
-        let mut profile: PlayerProfile = response.json().await?;
+    /// Reads a profile out of a Minecraft services response, the profile endpoint
+    /// and every skin/cape endpoint answer with the whole profile.
+    async fn from_response(
+        response: reqwest::Response,
+        access_token: String,
+        endpoints: AuthEndpoints,
+    ) -> Result<PlayerProfile, AuthServiceError> {
+        let mut profile: PlayerProfile = check_profile_status(response)?.json().await?;
         profile.access_token = access_token;
+        profile.endpoints = endpoints;
         Ok(profile)
     }
 }
@@ -0,0 +1,114 @@
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sl_utils::utils::http;
+
+use super::{
+    accounts::validate_username, check_profile_status, ensure_online, AuthServiceError,
+    PlayerProfile,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NameAvailability {
+    Available,
+    /// Another player already has the name
+    Duplicate,
+    /// The name is blocked, e.g. for containing a slur
+    NotAllowed,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameAvailabilityResponse {
+    status: NameAvailability,
+}
+
+/// Whether the player may change their name, at most once every 30 days.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameChangeStatus {
+    /// When the name was last changed
+    pub changed_at: Option<String>,
+    /// When the profile was created
+    pub created_at: Option<String>,
+    pub name_change_allowed: bool,
+}
+
+/// Body of a failed name change, `details` says why.
+#[derive(Debug, Default, Deserialize)]
+struct NameErrorResponse {
+    #[serde(default)]
+    details: Option<NameAvailabilityResponse>,
+}
+
+impl PlayerProfile {
+    /// Looks up whether `name` could be taken by this player.
+    pub async fn name_availability(
+        &self,
+        name: &str,
+    ) -> Result<NameAvailability, AuthServiceError> {
+        validate_username(name)?;
+        ensure_online()?;
+
+        let response = http::client()
+            .get(
+                self.endpoints
+                    .minecraft(&format!("/minecraft/profile/name/{name}/available")),
+            )
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        let response: NameAvailabilityResponse = check_profile_status(response)?.json().await?;
+        Ok(response.status)
+    }
+
+    pub async fn name_change_status(&self) -> Result<NameChangeStatus, AuthServiceError> {
+        ensure_online()?;
+        let response = http::client()
+            .get(self.endpoints.minecraft("/minecraft/profile/namechange"))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        Ok(check_profile_status(response)?.json().await?)
+    }
+
+    /// Renames the player to `name`.
+    pub async fn change_name(&mut self, name: &str) -> Result<(), AuthServiceError> {
+        validate_username(name)?;
+        ensure_online()?;
+
+        let response = http::client()
+            .put(
+                self.endpoints
+                    .minecraft(&format!("/minecraft/profile/name/{name}")),
+            )
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::BAD_REQUEST || status == StatusCode::FORBIDDEN {
+            let error: NameErrorResponse = response.json().await.unwrap_or_default();
+
+            return Err(match error.details.map(|details| details.status) {
+                Some(NameAvailability::Duplicate) => AuthServiceError::NameTaken(name.to_owned()),
+                Some(NameAvailability::NotAllowed) => {
+                    AuthServiceError::NameNotAllowed(name.to_owned())
+                }
+                _ if status == StatusCode::BAD_REQUEST => {
+                    AuthServiceError::InvalidUsername(name.to_owned())
+                }
+                _ => AuthServiceError::NameChangeNotAllowed,
+            });
+        }
+
+        *self = Self::from_response(
+            check_profile_status(response)?,
+            self.access_token.clone(),
+            self.endpoints.clone(),
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use std::{io, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use futures::{stream::FuturesUnordered, StreamExt};
+use reqwest::{StatusCode, Url};
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use super::{
+    AuthFlow, AuthServiceError, AuthServiceErrorMessage, AuthTokenResponse, CancellationToken,
+    LoginEvent,
+};
+
+/// How long the browser login waits for the redirect before giving up.
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a single connection to the listener may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const REDIRECT_PAGE: &str = "<html><body><h2>Logged in</h2>\
+    <p>You can close this tab and go back to SynthLauncher.</p></body></html>";
+
+/// Random URL safe string of 43 characters, used for the PKCE verifier and the state.
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Query parameters of the redirect the browser sends to the loopback listener.
+#[derive(Debug, Default)]
+struct Redirect {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// Reads the request line of a single HTTP request and parses the redirect out of its query.
+async fn read_redirect(stream: &mut TcpStream) -> Result<Redirect, AuthServiceError> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or(AuthServiceError::InvalidRedirect)?;
+
+    let url = Url::parse(&format!("http://localhost{target}"))
+        .map_err(|_| AuthServiceError::InvalidRedirect)?;
+
+    let mut redirect = Redirect::default();
+    for (key, value) in url.query_pairs() {
+        match &*key {
+            "code" => redirect.code = Some(value.into_owned()),
+            "state" => redirect.state = Some(value.into_owned()),
+            "error" => redirect.error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    Ok(redirect)
+}
+
+/// Answers a single connection, with `REDIRECT_PAGE` if it carries the OAuth redirect.
+async fn handle_connection(mut stream: TcpStream) -> Result<Option<Redirect>, AuthServiceError> {
+    let redirect = tokio::time::timeout(REQUEST_TIMEOUT, read_redirect(&mut stream))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+    let is_redirect = redirect.code.is_some() || redirect.error.is_some();
+
+    let response = if is_redirect {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{REDIRECT_PAGE}",
+            REDIRECT_PAGE.len()
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(is_redirect.then_some(redirect))
+}
+
+/// Accepts connections until one of them carries the OAuth redirect. Browsers may request
+/// other paths such as `/favicon.ico` first, or open connections they never send anything on.
+async fn wait_for_redirect(listener: &TcpListener) -> Result<Redirect, AuthServiceError> {
+    let mut connections = FuturesUnordered::new();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                connections.push(handle_connection(stream));
+            }
+            // Connections without the redirect, or broken ones, aren't the browser's
+            Some(handled) = connections.next() => {
+                if let Ok(Some(redirect)) = handled {
+                    return Ok(redirect);
+                }
+            }
+        }
+    }
+}
+
+impl AuthFlow {
+    /// Logs in through the authorization code flow with PKCE.
+    /// `on_event` receives `LoginEvent::OpenBrowser` with the URL the user has to open,
+    /// Microsoft then redirects the browser to a one-shot listener on localhost.
+    /// Stops with `AuthServiceError::Cancelled` as soon as `cancel` is cancelled.
+    pub async fn browser_login_with(
+        &mut self,
+        cancel: &CancellationToken,
+        mut on_event: impl FnMut(LoginEvent),
+    ) -> Result<&AuthTokenResponse, AuthServiceError> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let redirect_uri = format!("http://localhost:{}", listener.local_addr()?.port());
+
+        let verifier = random_string();
+        let state = random_string();
+
+        let url = Url::parse_with_params(
+            &self.endpoints.authorize(),
+            &[
+                ("client_id", self.client_id.as_str()),
+                ("response_type", "code"),
+                ("redirect_uri", &redirect_uri),
+                ("response_mode", "query"),
+                ("scope", "XboxLive.signin offline_access"),
+                ("code_challenge", &code_challenge(&verifier)),
+                ("code_challenge_method", "S256"),
+                ("state", &state),
+                ("prompt", "select_account"),
+            ],
+        )
+        .map_err(|_| AuthServiceError::InvalidRedirect)?;
+
+        on_event(LoginEvent::OpenBrowser {
+            url: url.to_string(),
+        });
+
+        let redirect = tokio::select! {
+            _ = cancel.cancelled() => return Err(AuthServiceError::Cancelled),
+            _ = tokio::time::sleep(REDIRECT_TIMEOUT) => return Err(AuthServiceError::LoginTimedOut),
+            redirect = wait_for_redirect(&listener) => redirect?,
+        };
+
+        if redirect.state.as_deref() != Some(&state) {
+            return Err(AuthServiceError::InvalidRedirect);
+        }
+
+        let code = match (redirect.code, redirect.error) {
+            (Some(code), None) => code,
+            (_, Some(error)) if error == "access_denied" => {
+                return Err(AuthServiceError::InvalidAccessToken)
+            }
+            _ => return Err(AuthServiceError::InvalidRedirect),
+        };
+
+        let response = self
+            .client
+            .post(self.endpoints.token())
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("scope", "XboxLive.signin offline_access"),
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", &redirect_uri),
+                ("code_verifier", &verifier),
+            ])
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let response: AuthTokenResponse = response.json().await?;
+                self.auth_token_res = Some(response);
+                Ok(self.auth_token_res.as_ref().unwrap())
+            }
+            StatusCode::BAD_REQUEST => {
+                let error: AuthServiceErrorMessage = response.json().await?;
+                match &error.error as &str {
+                    "invalid_grant" => Err(AuthServiceError::InvalidAccessToken),
+                    _ => Err(AuthServiceError::UnknownError),
+                }
+            }
+            _ => Err(AuthServiceError::UnknownError),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{config::config::Config, LAUNCHER_DIR};
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Known plaintext stored encrypted next to the secrets, used to tell a wrong key
+/// apart from a corrupted entry.
+const VERIFIER: &[u8] = b"synthlauncher";
+
+/// Environment variable holding the passphrase the secrets are encrypted with.
+pub const PASSPHRASE_ENV: &str = "SYNTHLAUNCHER_PASSPHRASE";
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("The key doesn't match the one the secrets were encrypted with.")]
+    WrongKey,
+
+    #[error("The key file {0} is invalid, it must contain exactly 32 bytes.")]
+    InvalidKeyFile(PathBuf),
+
+    #[error("Failed to derive a key from the passphrase: {0}")]
+    KeyDerivation(argon2::Error),
+
+    #[error("The secret {0} is corrupted.")]
+    Corrupted(String),
+
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Storage for secrets such as account tokens.
+/// Implemented by the encrypted file backend, a Secret Service/keyring backend could be added later.
+pub trait SecretBackend: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError>;
+    fn set(&self, key: &str, secret: &str) -> Result<(), SecretError>;
+    fn delete(&self, key: &str) -> Result<(), SecretError>;
+    fn keys(&self) -> Result<Vec<String>, SecretError>;
+}
+
+/// Where the encryption key of a `FileSecretBackend` comes from.
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    /// A key derived from a user supplied passphrase with Argon2id
+    Passphrase(String),
+    /// A file holding the raw 32 bytes key, generated if it doesn't exist
+    KeyFile(PathBuf),
+}
+
+impl KeySource {
+    fn derive(&self, salt: &[u8]) -> Result<[u8; KEY_LEN], SecretError> {
+        let mut key = [0u8; KEY_LEN];
+
+        match self {
+            KeySource::Passphrase(passphrase) => {
+                Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(SecretError::KeyDerivation)?;
+            }
+            KeySource::KeyFile(path) => {
+                if !path.exists() {
+                    OsRng.fill_bytes(&mut key);
+                    write_private(path, &key)?;
+                    return Ok(key);
+                }
+
+                let bytes = fs::read(path)?;
+                key = bytes
+                    .try_into()
+                    .map_err(|_| SecretError::InvalidKeyFile(path.clone()))?;
+            }
+        }
+
+        Ok(key)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SecretsFile {
+    salt: String,
+    verifier: String,
+    entries: HashMap<String, String>,
+}
+
+/// Secrets encrypted with XChaCha20-Poly1305 and kept in a single JSON file.
+pub struct FileSecretBackend {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+    file: Mutex<SecretsFile>,
+}
+
+impl FileSecretBackend {
+    /// Opens the secrets file at `path`, creating it if it doesn't exist.
+    /// Fails with `SecretError::WrongKey` if `source` isn't the key the file was created with.
+    pub fn open(path: PathBuf, source: KeySource) -> Result<Self, SecretError> {
+        let existing = if path.exists() {
+            Some(serde_json::from_str::<SecretsFile>(&fs::read_to_string(
+                &path,
+            )?)?)
+        } else {
+            None
+        };
+
+        let salt = match existing {
+            Some(ref file) => STANDARD
+                .decode(&file.salt)
+                .map_err(|_| SecretError::Corrupted("salt".to_string()))?,
+            None => {
+                let mut salt = vec![0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                salt
+            }
+        };
+
+        let key = source.derive(&salt)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+
+        let file = match existing {
+            Some(file) => {
+                if decrypt(&cipher, &file.verifier).as_deref() != Some(VERIFIER) {
+                    return Err(SecretError::WrongKey);
+                }
+                file
+            }
+            None => {
+                let file = SecretsFile {
+                    salt: STANDARD.encode(&salt),
+                    verifier: encrypt(&cipher, VERIFIER),
+                    entries: HashMap::new(),
+                };
+                write_private(&path, serde_json::to_string_pretty(&file)?.as_bytes())?;
+                file
+            }
+        };
+
+        Ok(Self {
+            path,
+            cipher,
+            file: Mutex::new(file),
+        })
+    }
+
+    fn save(&self, file: &SecretsFile) -> Result<(), SecretError> {
+        write_private(&self.path, serde_json::to_string_pretty(file)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl SecretBackend for FileSecretBackend {
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        let file = self.file.lock().unwrap();
+
+        let Some(entry) = file.entries.get(key) else {
+            return Ok(None);
+        };
+
+        let plaintext =
+            decrypt(&self.cipher, entry).ok_or_else(|| SecretError::Corrupted(key.to_string()))?;
+        let secret =
+            String::from_utf8(plaintext).map_err(|_| SecretError::Corrupted(key.to_string()))?;
+
+        Ok(Some(secret))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), SecretError> {
+        let mut file = self.file.lock().unwrap();
+        file.entries
+            .insert(key.to_string(), encrypt(&self.cipher, secret.as_bytes()));
+        self.save(&file)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), SecretError> {
+        let mut file = self.file.lock().unwrap();
+        if file.entries.remove(key).is_some() {
+            self.save(&file)?;
+        }
+        Ok(())
+    }
+
+    fn keys(&self) -> Result<Vec<String>, SecretError> {
+        Ok(self.file.lock().unwrap().entries.keys().cloned().collect())
+    }
+}
+
+fn encrypt(cipher: &XChaCha20Poly1305, plaintext: &[u8]) -> String {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .expect("encrypting into a Vec can't fail");
+
+    STANDARD.encode([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn decrypt(cipher: &XChaCha20Poly1305, encoded: &str) -> Option<Vec<u8>> {
+    let bytes = STANDARD.decode(encoded).ok()?;
+    if bytes.len() < NONCE_LEN {
+        return None;
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    cipher.decrypt(XNonce::from_slice(nonce), ciphertext).ok()
+}
+
+/// Writes `contents` to `path`, readable only by the current user.
+/// Goes through a temporary file created with those permissions, so the secrets are never
+/// readable by others, nor left half written.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+
+    // Left over by an interrupted write, maybe with other permissions
+    if temp.exists() {
+        fs::remove_file(&temp)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(temp, path)
+}
+
+/// Opens the secrets file in the launcher directory.
+/// The key comes from the `SYNTHLAUNCHER_PASSPHRASE` environment variable if set,
+/// otherwise from the `secrets_key_file` entry of the global config,
+/// otherwise from a key file generated in the launcher directory.
+pub fn default_backend() -> Result<FileSecretBackend, SecretError> {
+    let source = match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => KeySource::Passphrase(passphrase),
+        Err(_) => {
+            let key_file = Config::read_global()?
+                .get("secrets_key_file")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| LAUNCHER_DIR.join("secrets.key"));
+            KeySource::KeyFile(key_file)
+        }
+    };
+
+    FileSecretBackend::open(LAUNCHER_DIR.join("secrets.json"), source)
+}
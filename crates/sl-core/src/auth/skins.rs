@@ -0,0 +1,179 @@
+use std::{fs, path::Path};
+
+use reqwest::{
+    multipart::{Form, Part},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sl_utils::utils::http;
+
+use super::{ensure_online, AuthServiceError, PlayerProfile};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Arm width of a skin, `Slim` being the 3 pixels wide "Alex" model.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SkinVariant {
+    #[default]
+    Classic,
+    Slim,
+}
+
+impl SkinVariant {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SkinVariant::Classic => "classic",
+            SkinVariant::Slim => "slim",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TextureState {
+    Active,
+    Inactive,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Skin {
+    pub id: String,
+    pub state: TextureState,
+    pub url: String,
+    pub variant: SkinVariant,
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cape {
+    pub id: String,
+    pub state: TextureState,
+    pub url: String,
+    pub alias: String,
+}
+
+/// Checks `png` is a PNG the game accepts as a skin: 64x64, or 64x32 for the legacy format.
+pub fn validate_skin(png: &[u8]) -> Result<(), AuthServiceError> {
+    // The IHDR chunk always comes first, right after the signature and its own length and type
+    if png.len() < 24 || !png.starts_with(PNG_SIGNATURE) || &png[12..16] != b"IHDR" {
+        return Err(AuthServiceError::InvalidSkin(
+            "the file is not a PNG image".to_string(),
+        ));
+    }
+
+    let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+
+    if width != 64 || (height != 64 && height != 32) {
+        return Err(AuthServiceError::InvalidSkin(format!(
+            "the image is {width}x{height}, it must be 64x64 or 64x32"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Body of a request the Minecraft services refused.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServicesErrorResponse {
+    error_message: Option<String>,
+}
+
+impl PlayerProfile {
+    pub fn active_skin(&self) -> Option<&Skin> {
+        self.skins
+            .iter()
+            .find(|skin| skin.state == TextureState::Active)
+    }
+
+    pub fn active_cape(&self) -> Option<&Cape> {
+        self.capes
+            .iter()
+            .find(|cape| cape.state == TextureState::Active)
+    }
+
+    /// Uploads the PNG at `path` as the player's skin.
+    pub async fn upload_skin(
+        &mut self,
+        path: &Path,
+        variant: SkinVariant,
+    ) -> Result<(), AuthServiceError> {
+        let png = fs::read(path)?;
+        validate_skin(&png)?;
+        ensure_online()?;
+
+        let file = Part::bytes(png)
+            .file_name("skin.png")
+            .mime_str("image/png")?;
+        let form = Form::new()
+            .text("variant", variant.as_str())
+            .part("file", file);
+
+        let response = http::client()
+            .post(self.endpoints.minecraft("/minecraft/profile/skins"))
+            .bearer_auth(&self.access_token)
+            .multipart(form)
+            .send()
+            .await?;
+
+        // The skin passed the checks above but the services still refused it
+        if response.status() == StatusCode::BAD_REQUEST {
+            let error: ServicesErrorResponse = response.json().await.unwrap_or_default();
+            return Err(AuthServiceError::InvalidSkin(
+                error
+                    .error_message
+                    .unwrap_or_else(|| "the skin was rejected".to_string()),
+            ));
+        }
+
+        self.update(response).await
+    }
+
+    /// Goes back to the default skin.
+    pub async fn reset_skin(&mut self) -> Result<(), AuthServiceError> {
+        ensure_online()?;
+        let response = http::client()
+            .delete(self.endpoints.minecraft("/minecraft/profile/skins/active"))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?;
+
+        self.update(response).await
+    }
+
+    /// Shows the cape with the id or name `cape`, or hides the cape if `None`.
+    pub async fn set_cape(&mut self, cape: Option<&str>) -> Result<(), AuthServiceError> {
+        ensure_online()?;
+        let url = self.endpoints.minecraft("/minecraft/profile/capes/active");
+        let client = http::client();
+
+        let request = match cape {
+            Some(cape) => {
+                let id = &self
+                    .capes
+                    .iter()
+                    .find(|owned| owned.id == cape || owned.alias.eq_ignore_ascii_case(cape))
+                    .ok_or_else(|| AuthServiceError::CapeNotFound(cape.to_owned()))?
+                    .id;
+
+                client.put(url).json(&json!({ "capeId": id }))
+            }
+            None => client.delete(url),
+        };
+
+        let response = request.bearer_auth(&self.access_token).send().await?;
+        self.update(response).await
+    }
+
+    /// Replaces the profile with the one the skin and cape endpoints answer with.
+    async fn update(&mut self, response: reqwest::Response) -> Result<(), AuthServiceError> {
+        *self = Self::from_response(response, self.access_token.clone(), self.endpoints.clone())
+            .await?;
+        Ok(())
+    }
+}
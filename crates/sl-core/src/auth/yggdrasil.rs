@@ -0,0 +1,423 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{header::HeaderMap, Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sl_utils::utils::{
+    download::{lock_file, Downloader, Expected},
+    http,
+};
+
+use super::{ensure_online, secrets::SecretBackend, AuthServiceError, CancellationToken};
+use crate::AUTHLIB_INJECTOR_PATH;
+
+/// Latest authlib-injector build, as published by its authors.
+const AUTHLIB_INJECTOR_LATEST: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+
+/// Prefix of the secret backend keys holding Yggdrasil tokens.
+pub(super) const YGGDRASIL_SECRET_PREFIX: &str = "yggdrasil:";
+
+/// Header a server's homepage can use to point to its Yggdrasil API (API Location Indication).
+const API_LOCATION_HEADER: &str = "x-authlib-injector-api-location";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YggdrasilProfile {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YggdrasilSession {
+    pub access_token: String,
+    pub client_token: String,
+    pub selected_profile: Option<YggdrasilProfile>,
+    #[serde(default)]
+    pub available_profiles: Vec<YggdrasilProfile>,
+}
+
+/// Body of a failed Yggdrasil request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YggdrasilErrorResponse {
+    #[serde(default)]
+    error_message: String,
+}
+
+/// Client for a Yggdrasil compatible authentication server, such as the ones
+/// supported by authlib-injector.
+pub struct YggdrasilClient {
+    /// Root of the Yggdrasil API, `/authserver/...` is appended to it
+    api_url: String,
+    client: Client,
+}
+
+impl YggdrasilClient {
+    pub fn new(api_url: &str) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            client: http::client(),
+        }
+    }
+
+    /// Resolves the API root from the URL a user entered, following the
+    /// `X-Authlib-Injector-API-Location` header if the server sends one.
+    pub async fn discover(url: &str) -> Result<Self, AuthServiceError> {
+        ensure_online()?;
+        let url = if url.starts_with("http://") || url.starts_with("https://") {
+            url.to_string()
+        } else {
+            format!("https://{url}")
+        };
+
+        let response = http::client().get(&url).send().await?;
+        let api_url = api_location(&url, response.headers()).unwrap_or(url);
+
+        Ok(Self::new(&api_url))
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    fn authserver(&self, path: &str) -> String {
+        format!("{}/authserver/{path}", self.api_url)
+    }
+
+    /// Posts `body` to the authserver, a `403 Forbidden` fails with `forbidden`.
+    async fn post(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+        forbidden: AuthServiceError,
+    ) -> Result<reqwest::Response, AuthServiceError> {
+        ensure_online()?;
+        let response = self
+            .client
+            .post(self.authserver(path))
+            .json(&body)
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::FORBIDDEN => Err(forbidden),
+            status if status.is_client_error() => {
+                let error: YggdrasilErrorResponse = response.json().await?;
+                Err(AuthServiceError::Yggdrasil(error.error_message))
+            }
+            status => Err(AuthServiceError::UnexpectedStatus(status)),
+        }
+    }
+
+    /// Logs in with a username (usually an email) and password.
+    pub async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        client_token: &str,
+    ) -> Result<YggdrasilSession, AuthServiceError> {
+        let response = self
+            .post(
+                "authenticate",
+                json!({
+                    "agent": { "name": "Minecraft", "version": 1 },
+                    "username": username,
+                    "password": password,
+                    "clientToken": client_token,
+                    "requestUser": true
+                }),
+                AuthServiceError::InvalidCredentials,
+            )
+            .await?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Exchanges a session for a new access token, the old one stops working.
+    pub async fn refresh(
+        &self,
+        access_token: &str,
+        client_token: &str,
+    ) -> Result<YggdrasilSession, AuthServiceError> {
+        let response = self
+            .post(
+                "refresh",
+                json!({
+                    "accessToken": access_token,
+                    "clientToken": client_token,
+                    "requestUser": true
+                }),
+                AuthServiceError::InvalidAccessToken,
+            )
+            .await?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Whether `access_token` can still be used to join servers.
+    pub async fn validate(
+        &self,
+        access_token: &str,
+        client_token: &str,
+    ) -> Result<bool, AuthServiceError> {
+        let result = self
+            .post(
+                "validate",
+                json!({ "accessToken": access_token, "clientToken": client_token }),
+                AuthServiceError::InvalidAccessToken,
+            )
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(AuthServiceError::InvalidAccessToken) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Revokes `access_token`, used when an account is removed.
+    pub async fn invalidate(
+        &self,
+        access_token: &str,
+        client_token: &str,
+    ) -> Result<(), AuthServiceError> {
+        self.post(
+            "invalidate",
+            json!({ "accessToken": access_token, "clientToken": client_token }),
+            AuthServiceError::InvalidAccessToken,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// The server's metadata as is, passed to authlib-injector so it doesn't
+    /// have to fetch it again when the game starts.
+    pub async fn metadata(&self) -> Result<String, AuthServiceError> {
+        ensure_online()?;
+        let response = self.client.get(&self.api_url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(AuthServiceError::UnexpectedStatus(response.status()));
+        }
+
+        Ok(response.text().await?)
+    }
+}
+
+fn api_location(url: &str, headers: &HeaderMap) -> Option<String> {
+    let location = headers.get(API_LOCATION_HEADER)?.to_str().ok()?;
+    let resolved = Url::parse(url).ok()?.join(location).ok()?;
+    Some(resolved.to_string())
+}
+
+/// Tokens never end up in `accounts.json`, they're kept in the secret backend instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YggdrasilAccount {
+    /// Root of the server's Yggdrasil API
+    pub server: String,
+    pub uuid: String,
+    pub username: String,
+    /// Metadata of the server fetched at the last launch, reused if it can't be reached
+    #[serde(default)]
+    pub metadata: Option<String>,
+    #[serde(skip)]
+    pub access_token: String,
+    #[serde(skip)]
+    pub client_token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct YggdrasilTokens {
+    access_token: String,
+    client_token: String,
+}
+
+impl YggdrasilAccount {
+    /// Logs in on the server at `server`, which may be its homepage or its API root.
+    pub async fn login(
+        server: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<Self, AuthServiceError> {
+        ensure_online()?;
+        let client = YggdrasilClient::discover(server).await?;
+        let client_token = uuid_v4();
+
+        let session = client
+            .authenticate(username, password, &client_token)
+            .await?;
+        let profile = session
+            .selected_profile
+            .or_else(|| session.available_profiles.into_iter().next())
+            .ok_or(AuthServiceError::NoMinecraftProfile)?;
+
+        Ok(Self {
+            server: client.api_url().to_string(),
+            uuid: profile.id,
+            username: profile.name,
+            metadata: None,
+            access_token: session.access_token,
+            client_token: session.client_token,
+        })
+    }
+
+    fn client(&self) -> YggdrasilClient {
+        YggdrasilClient::new(&self.server)
+    }
+
+    /// Makes sure the access token is usable, refreshing it if the server rejects it.
+    pub async fn ensure_valid(&mut self) -> Result<(), AuthServiceError> {
+        let client = self.client();
+
+        if client
+            .validate(&self.access_token, &self.client_token)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let session = client
+            .refresh(&self.access_token, &self.client_token)
+            .await?;
+        self.access_token = session.access_token;
+        self.client_token = session.client_token;
+
+        if let Some(profile) = session.selected_profile {
+            self.username = profile.name;
+        }
+
+        Ok(())
+    }
+
+    pub async fn logout(&self) -> Result<(), AuthServiceError> {
+        self.client()
+            .invalidate(&self.access_token, &self.client_token)
+            .await
+    }
+
+    /// Gets everything the game needs to launch with this account:
+    /// a valid token, the server's metadata and the authlib-injector jar.
+    pub async fn prepare_launch(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<(), AuthServiceError> {
+        self.ensure_valid().await?;
+
+        match self.client().metadata().await {
+            Ok(metadata) => self.metadata = Some(metadata),
+            Err(_) if self.metadata.is_some() => {}
+            Err(err) => return Err(err),
+        }
+
+        tokio::select! {
+            _ = cancel.cancelled() => Err(AuthServiceError::Cancelled),
+            result = ensure_authlib_injector(&AUTHLIB_INJECTOR_PATH) => result,
+        }
+    }
+
+    /// The JVM arguments loading authlib-injector with the prefetched metadata.
+    pub fn jvm_arguments(&self, authlib_injector: &Path) -> Vec<String> {
+        let mut args = vec![format!(
+            "-javaagent:{}={}",
+            authlib_injector.display(),
+            self.server
+        )];
+
+        if let Some(ref metadata) = self.metadata {
+            args.push(format!(
+                "-Dauthlibinjector.yggdrasil.prefetched={}",
+                STANDARD.encode(metadata)
+            ));
+        }
+
+        args
+    }
+
+    pub(super) fn secret_key(&self) -> String {
+        format!("{YGGDRASIL_SECRET_PREFIX}{}", self.uuid)
+    }
+
+    pub(super) fn load_tokens(
+        &mut self,
+        secrets: &dyn SecretBackend,
+    ) -> Result<(), AuthServiceError> {
+        if let Some(secret) = secrets.get(&self.secret_key())? {
+            let tokens: YggdrasilTokens = serde_json::from_str(&secret)?;
+            self.access_token = tokens.access_token;
+            self.client_token = tokens.client_token;
+        }
+        Ok(())
+    }
+
+    pub(super) fn store_tokens(&self, secrets: &dyn SecretBackend) -> Result<(), AuthServiceError> {
+        let tokens = YggdrasilTokens {
+            access_token: self.access_token.clone(),
+            client_token: self.client_token.clone(),
+        };
+        secrets.set(&self.secret_key(), &serde_json::to_string(&tokens)?)?;
+        Ok(())
+    }
+}
+
+/// Random version 4 UUID without dashes, used as the client token.
+fn uuid_v4() -> String {
+    use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthlibInjectorArtifact {
+    download_url: String,
+    checksums: AuthlibInjectorChecksums,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthlibInjectorChecksums {
+    sha256: String,
+}
+
+/// Downloads the latest authlib-injector to `path` if it isn't there yet.
+/// The checksum is published next to the jar by the same host, it only catches corrupted downloads.
+pub async fn ensure_authlib_injector(path: &Path) -> Result<(), AuthServiceError> {
+    let _lock = lock_file(path).await;
+    if path.exists() {
+        return Ok(());
+    }
+
+    let downloader = Downloader::global();
+    let artifact: AuthlibInjectorArtifact =
+        serde_json::from_slice(&downloader.get_bytes(AUTHLIB_INJECTOR_LATEST).await?)?;
+
+    // Through a `.part` file renamed once complete, so an interrupted download is never used
+    downloader
+        .download(&artifact.download_url, path, &Expected::default())
+        .await?;
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    if !hash.eq_ignore_ascii_case(&artifact.checksums.sha256) {
+        fs::remove_file(path)?;
+        return Err(AuthServiceError::AuthlibInjectorChecksum);
+    }
+
+    Ok(())
+}
@@ -3,15 +3,30 @@ use std::{
     fs::{self, File},
     io::BufReader,
     path::PathBuf,
+    time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sl_java_manager::{installer::installer::install_version, JavaInstallation};
-use sl_utils::utils::errors::{BackendError, JavaError};
+use sl_utils::utils::{
+    errors::{BackendError, ConfigError, JavaError},
+    http::HttpConfig,
+};
+use tokio::sync::Mutex;
 use velcro::hash_map_from;
 
-use crate::LAUNCHER_DIR;
+use crate::{
+    auth::secrets::{SecretBackend, SecretError},
+    LAUNCHER_DIR, MULTI_PATH_SEPARATOR,
+};
+
+/// Key of the proxy password in the secret backend.
+const PROXY_PASSWORD_SECRET: &str = "proxy_password";
+
+/// Held while looking for a Java and installing it if missing, so instances installing
+/// at the same time don't both install the same version.
+static JAVA_INSTALL_LOCK: Mutex<()> = Mutex::const_new(());
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config(HashMap<String, String>);
@@ -25,6 +40,7 @@ impl Config {
     }
 
     pub async fn create_config(java_version: u16) -> Result<Self, BackendError> {
+        let _lock = JAVA_INSTALL_LOCK.lock().await;
         let javas = JavaInstallation::get_installations().unwrap();
 
         for java in javas {
@@ -39,7 +55,11 @@ impl Config {
 
         let new_java_path = install_version(java_version, None, "jdk".to_string(), true)
             .await
-            .map_err(|_| BackendError::JavaError(JavaError::VersionNotFound(java_version)))?;
+            .map_err(|err| match err {
+                // e.g. offline, rather than a version Adoptium doesn't have
+                BackendError::DownloadError(err) => BackendError::DownloadError(err),
+                _ => BackendError::JavaError(JavaError::VersionNotFound(java_version)),
+            })?;
 
         let java_binary = if cfg!(windows) { "java.exe" } else { "java" };
         return Ok(Self(hash_map_from! {
@@ -48,6 +68,17 @@ impl Config {
     }
 }
 
+/// Stores the proxy password in `secrets`, or removes it if `None`.
+pub fn set_proxy_password(
+    secrets: &dyn SecretBackend,
+    password: Option<&str>,
+) -> Result<(), SecretError> {
+    match password {
+        Some(password) => secrets.set(PROXY_PASSWORD_SECRET, password),
+        None => secrets.delete(PROXY_PASSWORD_SECRET),
+    }
+}
+
 impl Config {
     pub fn new(map: HashMap<String, String>) -> Self {
         Self(map)
@@ -78,6 +109,23 @@ impl Config {
         Ok(config)
     }
 
+    /// Removes `key` from the global config file, and from this config.
+    pub fn remove_config_field(&mut self, key: &str) -> Result<(), std::io::Error> {
+        self.remove(key);
+
+        let config_path = LAUNCHER_DIR.join("config.json");
+
+        let config_data = fs::read_to_string(&config_path)?;
+        let mut json: Value = serde_json::from_str(&config_data)?;
+
+        if let Some(obj) = json.as_object_mut() {
+            obj.remove(key);
+        }
+
+        fs::write(&config_path, serde_json::to_string_pretty(&json)?)?;
+        Ok(())
+    }
+
     pub fn update_config_field(
         &mut self,
         key: &str,
@@ -100,6 +148,67 @@ impl Config {
         self.0.get(entry).map(|x| x.as_str())
     }
 
+    pub fn set(&mut self, entry: &str, value: &str) {
+        self.0.insert(entry.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, entry: &str) -> Option<String> {
+        self.0.remove(entry)
+    }
+
+    /// The HTTP settings of the global config: `proxy`, `proxy_username`,
+    /// `no_proxy`, `root_certificates` (paths separated like the classpath), `user_agent`,
+    /// and `connect_timeout` and `read_timeout` in seconds.
+    /// The proxy password is kept in the secret backend instead, see [`Config::proxy_password`].
+    pub fn http_config(&self) -> Result<HttpConfig, ConfigError> {
+        let string = |key: &str| {
+            self.get(key)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+        };
+        let secs = |key: &str| {
+            string(key)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map(Duration::from_secs)
+                        .map_err(|_| ConfigError::InvalidValue(key.to_owned(), value))
+                })
+                .transpose()
+        };
+
+        Ok(HttpConfig {
+            proxy: string("proxy"),
+            proxy_username: string("proxy_username"),
+            proxy_password: None,
+            no_proxy: string("no_proxy"),
+            root_certificates: string("root_certificates")
+                .map(|paths| {
+                    paths
+                        .split(MULTI_PATH_SEPARATOR)
+                        .filter(|path| !path.is_empty())
+                        .map(PathBuf::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            user_agent: string("user_agent"),
+            connect_timeout: secs("connect_timeout")?,
+            read_timeout: secs("read_timeout")?,
+        })
+    }
+
+    /// The proxy password, from `secrets`. One older versions left in the config
+    /// is moved there first, the config then has to be saved without it.
+    pub fn proxy_password(
+        &mut self,
+        secrets: &dyn SecretBackend,
+    ) -> Result<Option<String>, SecretError> {
+        if let Some(password) = self.remove("proxy_password") {
+            secrets.set(PROXY_PASSWORD_SECRET, &password)?;
+        }
+        secrets.get(PROXY_PASSWORD_SECRET)
+    }
+
     pub fn merge(self, mut other: Self) -> Self {
         other.0.extend(self.0);
         other
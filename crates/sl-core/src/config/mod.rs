@@ -2,9 +2,15 @@ pub mod config;
 
 use std::{env, fs::{self, OpenOptions}, path::PathBuf};
 
-use sl_utils::utils::errors::BackendError;
+use config::Config;
+use sl_utils::utils::{
+    download::Downloader,
+    errors::{BackendError, ConfigError},
+    http,
+    mirrors::Mirrors,
+};
 
-use crate::{json::manifest::fetch_version_manifest, ASSETS_DIR, INSTALLATIONS_DIR, INSTALLATIONS_PATH, LAUNCHER_DIR, LIBS_DIR};
+use crate::{auth::secrets::{self, SecretError}, json::manifest::fetch_version_manifest, ASSETS_DIR, INSTALLATIONS_DIR, INSTALLATIONS_PATH, LAUNCHER_DIR, LIBS_DIR, MIRRORS_PATH};
 
 pub fn config_launcher_dir() -> PathBuf {
     #[cfg(target_os = "windows")]
@@ -34,6 +40,34 @@ pub fn config_launcher_dir() -> PathBuf {
     }
 }
 
+/// Applies the HTTP settings and offline mode of the global config and the mirrors to every request of the launcher.
+pub fn init_http() -> Result<(), BackendError> {
+    fs::create_dir_all(&*LAUNCHER_DIR)?;
+
+    let mut config = Config::read_global()?;
+    if config.get("offline") == Some("true") {
+        http::set_offline(true);
+    }
+
+    let mut http_config = config.http_config()?;
+    // The secret backend is only opened when there's a password to read, it may need a passphrase
+    let legacy_password = config.get("proxy_password").is_some();
+    if http_config.proxy_username.is_some() || legacy_password {
+        let secret_error = |err: SecretError| ConfigError::Secret(err.to_string());
+        let secrets = secrets::default_backend().map_err(secret_error)?;
+        http_config.proxy_password = config.proxy_password(&secrets).map_err(secret_error)?;
+
+        if legacy_password {
+            config.remove_config_field("proxy_password")?;
+        }
+    }
+    http::set_client(http_config.build()?);
+
+    let mirrors = Mirrors::load(&MIRRORS_PATH)?;
+    Downloader::set_global(Downloader::default().mirrors(mirrors));
+    Ok(())
+}
+
 pub async fn init_launcher_dir() -> Result<(), BackendError> {
     fs::create_dir_all(&(*LAUNCHER_DIR)).unwrap();
     fs::create_dir_all(&(*LIBS_DIR)).unwrap();
@@ -46,7 +80,9 @@ pub async fn init_launcher_dir() -> Result<(), BackendError> {
         .append(true) 
         .open(&*INSTALLATIONS_PATH)?;
     
-    fetch_version_manifest().await;
+    init_http()?;
+    // Without a manifest, only what needs it fails, with `InstallationError::ManifestUnavailable`
+    let _ = fetch_version_manifest().await;
 
     Ok(())
 }
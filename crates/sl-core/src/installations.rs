@@ -5,23 +5,33 @@ use std::{
     io::{self, BufReader},
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::Mutex,
 };
 
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
 use sl_meta::json::{
     fabric::{self, profile::FabricLoaderProfile},
-    vanilla::Client,
+    vanilla::{Arguments, Client, LaunchFeatures},
     version_manifest::VersionType,
 };
-use sl_utils::utils::errors::{BackendError, DownloadError, InstallationError};
+use sl_utils::utils::{
+    download::get_as_bytes,
+    errors::{BackendError, DownloadError, InstallationError},
+    http,
+};
 
 use crate::{
-    auth::PlayerProfile,
+    auth::{accounts::Account, MICROSOFT_CLIENT_ID},
     config::config::Config,
-    json::{client, manifest::download_version},
-    ASSETS_DIR, INSTALLATIONS_DIR, INSTALLATIONS_PATH, LIBS_DIR, MANIFEST, MULTI_PATH_SEPARATOR,
-    TEMP_CLIENT,
+    json::{
+        client::{self, game_assets_dir, log_config_path, ClientInstaller, VerifyReport},
+        manifest::{download_version, manifest_version},
+    },
+    launch::{LaunchOptions, LaunchSettings, QuickPlay},
+    progress::{InstallPhase, ProgressReporter},
+    ASSETS_DIR, AUTHLIB_INJECTOR_PATH, INSTALLATIONS_DIR, INSTALLATIONS_PATH, LIBS_DIR,
+    MANIFEST_PATH, MULTI_PATH_SEPARATOR,
 };
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -40,20 +50,17 @@ pub struct Installation {
 }
 
 impl Installation {
-    pub fn new(name: &str, version: &str) -> Option<Self> {
-        MANIFEST
-            .versions()
-            .find(|x| x.id == version)
-            .and_then(|version| {
-                Some(Self {
-                    name: name.to_owned(),
-                    info: InstallationInfo {
-                        version: version.id.clone(),
-                        release_time: version.release_time.clone(),
-                        r#type: Some(version.r#type),
-                    },
-                })
-            })
+    pub fn new(name: &str, version: &str) -> Result<Self, BackendError> {
+        let version = manifest_version(version)?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            info: InstallationInfo {
+                version: version.id.clone(),
+                release_time: version.release_time.clone(),
+                r#type: Some(version.r#type),
+            },
+        })
     }
 
     pub fn get_installation_from_dir(name: &str) -> Result<Self, BackendError> {
@@ -104,17 +111,20 @@ impl Installation {
         Some(profile)
     }
 
-    pub async fn install_fabric(&mut self, loader_version: &str) -> Result<(), BackendError> {
+    pub async fn install_fabric(
+        &mut self,
+        loader_version: &str,
+        progress: &ProgressReporter,
+    ) -> Result<(), BackendError> {
         if self.fabric_json_path().is_some() {
             return Ok(());
         }
 
         let path = self.dir_path().join("fabric.json");
+        let progress = progress.phase(InstallPhase::Loader, 1, 0);
 
         let make_request = async |url: &str| -> Result<Vec<u8>, DownloadError> {
-            let response = reqwest::get(url).await?;
-            let bytes = response.bytes().await?;
-            Ok(bytes.to_vec())
+            Ok(get_as_bytes(url).await?.to_vec())
         };
 
         let profile = fabric::profile::get_loader_profile::<
@@ -122,10 +132,11 @@ impl Installation {
             DownloadError,
         >(&self.info.version, loader_version, make_request)
         .await?;
-        let file = File::create(&path)?;
-
-        serde_json::to_writer_pretty(file, &profile)?;
+        let profile = serde_json::to_vec_pretty(&profile)?;
+        fs::write(&path, &profile)?;
 
+        progress.file_done(&path, profile.len() as u64);
+        progress.finish();
         Ok(())
     }
 
@@ -152,6 +163,34 @@ impl Installation {
         Some(client)
     }
 
+    /// The account this instance always launches with, overriding the active account.
+    pub fn account(&self) -> Option<String> {
+        self.read_config()?.get("account").map(str::to_owned)
+    }
+
+    pub fn set_account(&mut self, account: Option<&str>) -> Result<(), std::io::Error> {
+        let mut config = self.read_config().unwrap_or_else(Config::empty);
+
+        match account {
+            Some(account) => config.set("account", account),
+            None => {
+                config.remove("account");
+            }
+        }
+
+        self.override_config(config)
+    }
+
+    pub fn launch_settings(&self) -> LaunchSettings {
+        LaunchSettings::from_config(&self.read_config().unwrap_or_else(Config::empty))
+    }
+
+    pub fn set_launch_settings(&mut self, settings: &LaunchSettings) -> Result<(), std::io::Error> {
+        let mut config = self.read_config().unwrap_or_else(Config::empty);
+        settings.write_config(&mut config);
+        self.override_config(config)
+    }
+
     fn override_config(&mut self, config: Config) -> Result<(), std::io::Error> {
         let installations_dir = self.dir_path();
         let config_path = self.config_path();
@@ -161,34 +200,60 @@ impl Installation {
         Ok(())
     }
 
-    async fn reinit(&mut self) -> Result<Client, BackendError> {
-        let client_raw = download_version(&self.info.version).await?;
+    async fn reinit(&mut self, progress: &ProgressReporter) -> Result<Client, BackendError> {
+        let manifest = progress.phase(InstallPhase::Manifest, 1, 0);
+        let version = manifest_version(&self.info.version)?;
+        manifest.file_done(&MANIFEST_PATH, 0);
+        manifest.finish();
+
+        let client_json = progress.phase(InstallPhase::ClientJson, 1, 0);
+        let client_raw = download_version(version).await?;
         let client: Client =
             serde_json::from_slice(&client_raw).expect("Failed to deserialize client.json!");
 
+        fs::create_dir_all(self.dir_path())?;
+        fs::write(self.client_json_path(), &client_raw)?;
+        client_json.file_done(&self.client_json_path(), client_raw.len() as u64);
+        client_json.finish();
+
+        let java = progress.phase(InstallPhase::Java, 1, 0);
         let config =
             Config::create_config(client.java_version.as_ref().unwrap().major_version).await?;
+        if let Some(java_path) = config.get("java") {
+            java.file_done(Path::new(java_path), 0);
+        }
+        java.finish();
+
         let config = config.merge(Config::read_global().unwrap());
         self.override_config(config)?;
 
-        fs::create_dir_all(self.dir_path())?;
-
-        fs::write(self.client_json_path(), &client_raw)?;
         Ok(client)
     }
 
-    pub async fn init(&mut self) -> Result<Client, BackendError> {
+    pub async fn init(&mut self, progress: &ProgressReporter) -> Result<Client, BackendError> {
         match self.read_client() {
             Some(client) => Ok(client),
-            None => self.reinit().await,
+            None => self.reinit(progress).await,
         }
     }
 
-    pub async fn install(&mut self) -> Result<(), BackendError> {
-        let client = self.init().await?;
-        *TEMP_CLIENT.lock().await = Some(client);
+    pub async fn install(&mut self, progress: &ProgressReporter) -> Result<(), BackendError> {
+        let client = self.init(progress).await?;
+        ClientInstaller::new(&client, &self.dir_path(), progress)
+            .install()
+            .await
+    }
+
+    /// Checks the size and SHA-1 of every file the installation needs, without fixing anything.
+    pub async fn verify(&mut self) -> Result<VerifyReport, BackendError> {
+        let client = self.init(&ProgressReporter::default()).await?;
+        Ok(client::verify_client(&client, &self.dir_path()))
+    }
 
-        client::install_client(self.dir_path()).await
+    /// Downloads again only the files [`Installation::verify`] reports as missing or corrupted.
+    pub async fn repair(&mut self) -> Result<VerifyReport, BackendError> {
+        let client = self.init(&ProgressReporter::default()).await?;
+        client::repair_client(&client, &self.dir_path()).await
     }
 
     fn classpath(&self, client: &Client) -> String {
@@ -213,8 +278,17 @@ impl Installation {
         classpath.join(MULTI_PATH_SEPARATOR)
     }
 
+    fn release_time(&self) -> Result<DateTime<FixedOffset>, InstallationError> {
+        DateTime::parse_from_rfc3339(&self.info.release_time).map_err(|_| {
+            InstallationError::InvalidReleaseTime(
+                self.info.version.clone(),
+                self.info.release_time.clone(),
+            )
+        })
+    }
+
     // Thanks MrMayMan
-    fn generate_sound_arguments(&self, jvm_args: &mut Vec<String>) {
+    fn generate_sound_arguments(&self, release: DateTime<FixedOffset>, jvm_args: &mut Vec<String>) {
         if self.info.r#type == Some(VersionType::OldBeta)
             || self.info.r#type == Some(VersionType::OldAlpha)
         {
@@ -235,10 +309,7 @@ impl Installation {
             jvm_args.push("-Djava.util.Arrays.useLegacyMergeSort=true".to_owned());
         } else {
             // 1.5.2 release date
-            let v1_5_2 = DateTime::parse_from_rfc3339("2013-04-25T15:45:00+00:00").unwrap();
-            let release = DateTime::parse_from_rfc3339(&self.info.release_time).unwrap();
-
-            if release <= v1_5_2 {
+            if release <= date("2013-04-25T15:45:00+00:00") {
                 // 1.0 - 1.5.2
                 jvm_args.push("-Dhttp.proxyHost=betacraft.uk".to_owned());
                 jvm_args.push("-Dhttp.proxyPort=11707".to_owned());
@@ -246,39 +317,124 @@ impl Installation {
         }
     }
 
+    /// Quick Play came with 23w14a, older versions can only be told which server to join.
+    fn supports_quick_play(release: DateTime<FixedOffset>) -> bool {
+        release >= date("2023-04-05T00:00:00+00:00")
+    }
+
+    /// Points log4j at the config of the version, which is what keeps the versions shipping
+    /// a vulnerable log4j safe from Log4Shell.
+    fn generate_logging_arguments(
+        client: &Client,
+        release: DateTime<FixedOffset>,
+        jvm_args: &mut Vec<String>,
+    ) {
+        if let (Some(config), Some(path)) = (client.logging_config(), log_config_path(client)) {
+            jvm_args.push(config.argument.replace("${path}", path.to_str().unwrap()));
+        }
+
+        // 1.17 to 1.18 ship a log4j recent enough to also turn the lookups off with a
+        // property, 1.18.1 ships one that's fixed
+        if release >= date("2021-06-08T11:00:40+00:00")
+            && release < date("2021-12-10T08:23:00+00:00")
+        {
+            jvm_args.push("-Dlog4j2.formatMsgNoLookups=true".to_owned());
+        }
+    }
+
+    /// The JVM and game arguments the version, the launch settings and `options` ask for,
+    /// before their `${...}` placeholders are filled in.
+    pub fn launch_arguments(
+        &self,
+        client: &Client,
+        settings: &LaunchSettings,
+        options: &LaunchOptions,
+    ) -> Result<(Vec<String>, Vec<String>), BackendError> {
+        let release = self.release_time()?;
+        let resolution = settings.resolution(options);
+        let quick_play = options.quick_play.as_ref();
+        let supports_quick_play = Self::supports_quick_play(release);
+        let features = LaunchFeatures {
+            is_demo_user: settings.demo,
+            has_custom_resolution: resolution.is_some(),
+            is_quick_play_singleplayer: supports_quick_play
+                && matches!(quick_play, Some(QuickPlay::Singleplayer(_))),
+            is_quick_play_multiplayer: supports_quick_play
+                && matches!(quick_play, Some(QuickPlay::Multiplayer(_))),
+            is_quick_play_realms: supports_quick_play
+                && matches!(quick_play, Some(QuickPlay::Realms(_))),
+            ..Default::default()
+        };
+
+        let raw_args = client.arguments.clone();
+        let legacy = matches!(raw_args, Arguments::MinecraftArgs(_));
+        let (mut jvm_args, mut game_args) = raw_args.into_raw(&features);
+        game_args.extend(settings.game_arguments(options, legacy));
+
+        if let Some(quick_play) = quick_play.filter(|_| !supports_quick_play) {
+            let args = quick_play.legacy_arguments().ok_or_else(|| {
+                InstallationError::QuickPlayUnsupported(self.info.version.clone())
+            })?;
+            game_args.extend(args);
+        }
+
+        self.generate_sound_arguments(release, &mut jvm_args);
+        Self::generate_logging_arguments(client, release, &mut jvm_args);
+
+        Ok((jvm_args, game_args))
+    }
+
     fn generate_arguments(
         &self,
         config: &Config,
-        profile: Option<&PlayerProfile>,
+        account: &Account,
+        options: &LaunchOptions,
     ) -> Result<Vec<String>, BackendError> {
-        let global_config = Config::read_global().unwrap();
         let client = self.read_client().expect("Failed to read client.json!");
         let classpath = self.classpath(&client);
         let game_dir = self.dir_path();
         let natives_dir = game_dir.join(".natives");
+        let game_assets = game_assets_dir(&client, &game_dir);
 
-        let raw_args = client.arguments;
-        let (mut jvm_args, mut game_args) = raw_args.into_raw();
+        // Without its log config a version shipping a vulnerable log4j is open to Log4Shell
+        if let (Some(config), Some(path)) = (client.logging_config(), log_config_path(&client)) {
+            if client::check_file(&config.file.download, &path) != client::FileStatus::Valid {
+                return Err(InstallationError::LogConfigUnavailable(self.name.clone()).into());
+            }
+        }
 
-        let regex = regex::Regex::new(r"\$\{(\w+)\}").expect("Failed to compile regex!");
+        let settings = LaunchSettings::from_config(config);
+        let (width, height) = settings
+            .resolution(options)
+            .map(|(width, height)| (width.to_string(), height.to_string()))
+            .unwrap_or_default();
+        let quick_play = options.quick_play.as_ref();
+        let (mut jvm_args, mut game_args) = self.launch_arguments(&client, &settings, options)?;
 
-        self.generate_sound_arguments(&mut jvm_args);
+        let regex = regex::Regex::new(r"\$\{(\w+)\}").expect("Failed to compile regex!");
 
         let fmt_arg = |arg: &str| {
             Some(match arg {
                 "game_directory" => game_dir.to_str().unwrap(),
-                "assets_root" | "game_assets" => ASSETS_DIR.to_str().unwrap(),
+                "assets_root" => ASSETS_DIR.to_str().unwrap(),
+                "game_assets" => game_assets.to_str().unwrap(),
                 "assets_index_name" => &client.assets,
                 "version_name" => &self.info.version,
                 "classpath" => classpath.as_str(),
                 "natives_directory" => natives_dir.to_str().unwrap(),
-                "auth_uuid" => profile.map(|m| m.uuid.as_str()).unwrap_or("0"),
-                "auth_access_token" => profile.map(|m| m.access_token.as_str()).unwrap_or("0"),
-                "auth_player_name" => profile
-                    .map(|m| m.username.as_str())
-                    .unwrap_or(global_config.get("auth_player_name").unwrap()),
-                "clientid" => "74909cec-49b6-4fee-aa60-1b2a57ef72e1", // Please don't steal :(
+                "auth_uuid" => account.uuid(),
+                "auth_access_token" | "auth_session" => account.access_token(),
+                "auth_player_name" => account.username(),
+                "auth_xuid" => account.xuid(),
+                "user_type" => account.user_type(),
+                "user_properties" => "{}",
+                "clientid" => MICROSOFT_CLIENT_ID,
                 "version_type" => "SynthLauncher",
+                "resolution_width" => &width,
+                "resolution_height" => &height,
+                "quickPlaySingleplayer" | "quickPlayMultiplayer" | "quickPlayRealms" => {
+                    quick_play?.target()
+                }
                 _ => config.get(arg)?,
             })
         };
@@ -299,12 +455,26 @@ impl Installation {
         fmt_args(&mut game_args);
         fmt_args(&mut jvm_args);
 
+        if let Account::Yggdrasil(yggdrasil) = account {
+            jvm_args.extend(yggdrasil.jvm_arguments(&AUTHLIB_INJECTOR_PATH));
+        }
+
         jvm_args.push(client.main_class.clone());
 
         Ok([jvm_args, game_args].concat())
     }
 
-    pub fn execute(&self, profile: Option<&PlayerProfile>) -> Result<(), BackendError> {
+    pub async fn execute(
+        &self,
+        account: &Account,
+        options: &LaunchOptions,
+    ) -> Result<(), BackendError> {
+        // Offline, a missing log config is left for the launch to refuse
+        if !http::is_offline() {
+            let client = self.read_client().expect("Failed to read client.json!");
+            client::download_log_config(&client).await?;
+        }
+
         let config = self.read_config().unwrap();
 
         let current_java_path = config.get("java").unwrap();
@@ -314,9 +484,12 @@ impl Installation {
         let max_ram = config.get("max_ram").unwrap_or("2048");
         let min_ram = config.get("min_ram").unwrap_or("1024");
 
-        let args = self.generate_arguments(&config, profile)?;
+        let args = self.generate_arguments(&config, account, options)?;
 
-        println!("Launching with args: {:?}", &args);
+        println!(
+            "Launching with args: {:?}",
+            redact_access_token(&args, account)
+        );
 
         let output = Command::new(current_java_path)
             .arg(format!("-Xmx{}M", max_ram))
@@ -337,10 +510,30 @@ impl Installation {
     }
 }
 
+/// A release time Minecraft versions are compared against.
+fn date(rfc3339: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(rfc3339).expect("Invalid date!")
+}
+
+/// `args` with the access token of `account` hidden, for logging them.
+fn redact_access_token(args: &[String], account: &Account) -> Vec<String> {
+    let token = account.access_token();
+    if matches!(account, Account::Offline(_)) || token.is_empty() {
+        return args.to_vec();
+    }
+
+    args.iter()
+        .map(|arg| arg.replace(token, "<access token>"))
+        .collect()
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(transparent)]
 pub struct Installations(pub Vec<Installation>);
 
+/// Held while `installations.json` is read then written back, so concurrent changes don't drop each other.
+static INSTALLATIONS_LOCK: Mutex<()> = Mutex::new(());
+
 impl Installations {
     pub fn new() -> Self {
         Installations(Vec::new())
@@ -363,6 +556,7 @@ impl Installations {
     }
 
     pub fn add(installation: &Installation) -> io::Result<()> {
+        let _lock = INSTALLATIONS_LOCK.lock().unwrap();
         let mut existing_installations = Self::load()?;
 
         if !existing_installations
@@ -379,6 +573,7 @@ impl Installations {
     }
 
     pub fn remove(name: &str) -> io::Result<()> {
+        let _lock = INSTALLATIONS_LOCK.lock().unwrap();
         let mut existing_installations = Self::load()?;
 
         existing_installations
@@ -1,202 +1,532 @@
 use std::{
-    fs::{self, File},
-    path::{Path, PathBuf},
+    collections::HashMap,
+    fs,
+    future::Future,
+    path::{Component, Path, PathBuf},
 };
 
 use bytes::Bytes;
 use futures::{stream::FuturesUnordered, StreamExt};
-use sha1::{Digest, Sha1};
+use serde::Serialize;
 use sl_meta::json::vanilla::{AssetIndex, AssetObject, Client, Download, Library};
 use sl_utils::utils::{
-    self,
-    errors::{BackendError, DownloadError},
+    download::{lock_file, sha1_hex, Downloader, Expected},
+    errors::{BackendError, DownloadError, InstallationError},
     zip::ZipExtractor,
 };
 
-use crate::{ASSETS_DIR, LIBS_DIR, TEMP_CLIENT};
+use crate::{
+    progress::{InstallPhase, PhaseProgress, ProgressReporter},
+    ASSETS_DIR, LIBS_DIR,
+};
 
-#[inline(always)]
-fn verify_data(file: &mut File, sha1: &str) -> bool {
-    let mut hasher = Sha1::new();
+/// State of a file on disk compared to the size and SHA-1 its download expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Valid,
+    Missing,
+    Corrupted,
+}
+
+fn expected(download: &Download) -> Expected {
+    Expected::new(
+        download.sha1.as_deref(),
+        download.size.map(|size| size as u64),
+    )
+}
+
+/// Checks `data` has the size and SHA-1 `download` expects, when it knows them.
+fn verify_data(data: &[u8], download: &Download) -> bool {
+    expected(download).matches(data)
+}
 
-    let Ok(_) = std::io::copy(file, &mut hasher) else {
-        return false;
+/// Compares the file at `path` against the size and SHA-1 `download` expects.
+pub fn check_file(download: &Download, path: &Path) -> FileStatus {
+    let Ok(metadata) = fs::metadata(path) else {
+        return FileStatus::Missing;
     };
 
-    let hash = hasher.finalize();
-    if hash.as_slice() != sha1.as_bytes() {
-        return false;
+    // The size is checked first so most corrupted files aren't hashed at all
+    if download
+        .size
+        .is_some_and(|size| metadata.len() != size as u64)
+    {
+        return FileStatus::Corrupted;
     }
 
-    true
+    match fs::read(path) {
+        Ok(data) if verify_data(&data, download) => FileStatus::Valid,
+        _ => FileStatus::Corrupted,
+    }
 }
 
+/// Downloads `download` to `path` unless a valid copy is already there, returning its size.
+/// Another install downloading the same file is waited for rather than downloaded alongside.
 #[inline(always)]
-async fn download_and_verify(download: &Download, path: &Path) -> Result<(), DownloadError> {
-    if File::open(path).is_ok_and(|mut f| {
-        if let Some(sha1) = &download.sha1 {
-            verify_data(&mut f, sha1)
-        } else {
-            true
-        }
-    }) {
-        return Ok(());
+pub async fn download_and_verify(download: &Download, path: &Path) -> Result<u64, DownloadError> {
+    let _lock = lock_file(path).await;
+    if check_file(download, path) == FileStatus::Valid {
+        return Ok(fs::metadata(path)?.len());
     }
 
-    let data = utils::download::get_as_bytes(&download.url).await?;
-
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    fs::write(path, &data)?;
-    Ok(())
+    Downloader::global()
+        .download(&download.url, path, &expected(download))
+        .await
 }
 
-async fn download_and_read_file(download: &Download, path: &Path) -> Result<Bytes, DownloadError> {
+async fn download_and_read_file(
+    download: &Download,
+    path: &Path,
+    progress: &PhaseProgress,
+) -> Result<Bytes, DownloadError> {
     let full_path = if let Some(ref child) = download.path {
         &path.join(child)
     } else {
         path
     };
 
-    download_and_verify(download, full_path).await?;
+    let size = download_and_verify(download, full_path).await?;
+    progress.file_done(full_path, size);
     Ok(Bytes::from(
         fs::read(full_path).expect("get_download_in: failed to read downloaded file"),
     ))
 }
 
-async fn download_to(download: &Download, path: &Path) -> Result<(), DownloadError> {
+async fn download_to(
+    download: &Download,
+    path: &Path,
+    progress: &PhaseProgress,
+) -> Result<(), DownloadError> {
     let full_path = if let Some(ref child) = download.path {
         &path.join(child)
     } else {
         path
     };
 
-    download_and_verify(download, full_path).await
+    let size = download_and_verify(download, full_path).await?;
+    progress.file_done(full_path, size);
+    Ok(())
 }
 
+/// Runs `download` on every item at once, the downloader limits how many actually download.
 #[inline(always)]
-async fn download_futures<T, F, R, I>(to_download: I, download_max: usize, download: F) -> Vec<R>
+async fn download_futures<T, F, Fut, I>(to_download: I, download: F) -> Vec<Fut::Output>
 where
     I: Iterator<Item = T>,
-    F: AsyncFn(T) -> R,
+    F: Fn(T) -> Fut,
+    Fut: Future,
 {
-    let (size_0, size_1) = to_download.size_hint();
-    let size_hint = size_1.unwrap_or(size_0);
+    to_download
+        .map(download)
+        .collect::<FuturesUnordered<_>>()
+        .collect()
+        .await
+}
 
-    let mut outputs = Vec::with_capacity(size_hint.min(10));
-    let mut futures = FuturesUnordered::new();
+fn object_path(object: &AssetObject) -> PathBuf {
+    ASSETS_DIR
+        .join("objects")
+        .join(&object.hash[0..2])
+        .join(&object.hash)
+}
 
-    for item in to_download {
-        futures.push(download(item));
-        if futures.len() == download_max {
-            let next = futures.next().await.unwrap();
-            outputs.push(next);
-        }
+/// Asset objects are named after their SHA-1.
+fn object_download(object: &AssetObject) -> Download {
+    Download {
+        path: None,
+        url: format!(
+            "https://resources.download.minecraft.net/{}/{}",
+            &object.hash[0..2],
+            object.hash
+        ),
+        sha1: Some(object.hash.clone()),
+        size: Some(object.size as i32),
     }
+}
+
+fn asset_index_path(client: &Client) -> PathBuf {
+    ASSETS_DIR
+        .join("indexes")
+        .join(format!("{}.json", client.assets))
+}
 
-    while let Some(future) = futures.next().await {
-        outputs.push(future);
+/// The asset index of `client` if it was downloaded and is valid.
+fn read_asset_index(client: &Client) -> Option<AssetIndex> {
+    fs::read(asset_index_path(client))
+        .ok()
+        .filter(|data| verify_data(data, &client.asset_index))
+        .and_then(|data| serde_json::from_slice(&data).ok())
+}
+
+/// Where the versions with a `virtual` or `map_to_resources` asset index read the objects from by name,
+/// `None` for the ones reading the hashed objects.
+fn named_assets_dir(client: &Client, index: &AssetIndex, path: &Path) -> Option<PathBuf> {
+    if index.map_to_resources {
+        Some(path.join("resources"))
+    } else if index.r#virtual {
+        Some(ASSETS_DIR.join("virtual").join(&client.assets))
+    } else {
+        None
     }
+}
 
-    outputs
+/// Where the object `name` goes in `dir`, `None` if the name leads out of it.
+fn named_asset_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let name = Path::new(name);
+    name.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| dir.join(name))
 }
 
-async fn install_assets(
-    // client: &Client
-) -> Result<(), DownloadError> {
-    let id = (*TEMP_CLIENT).lock().await.as_ref().unwrap().assets.clone();
-    let indexes_dir = ASSETS_DIR.join("indexes");
-    let indexes_path = indexes_dir.join(format!("{}.json", id));
+/// What `${game_assets}` stands for with `client` in the instance at `path`.
+pub fn game_assets_dir(client: &Client, path: &Path) -> PathBuf {
+    read_asset_index(client)
+        .and_then(|index| named_assets_dir(client, &index, path))
+        .unwrap_or_else(|| ASSETS_DIR.clone())
+}
 
-    let download = download_and_read_file(&(*TEMP_CLIENT).lock().await.as_ref().unwrap().asset_index, &indexes_path).await?;
+/// Copies a downloaded object to `path` unless a valid copy is already there.
+async fn copy_object(object: &AssetObject, path: &Path) -> Result<(), DownloadError> {
+    let _lock = lock_file(path).await;
+    if check_file(&object_download(object), path) == FileStatus::Valid {
+        return Ok(());
+    }
 
-    let index: AssetIndex = serde_json::from_slice(&download).unwrap();
-    let objects = index.objects;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(object_path(object), path)?;
+    Ok(())
+}
 
-    let download_object = async |object: AssetObject| -> Result<(), DownloadError> {
-        let dir_name = &object.hash[0..2];
-        let dir = ASSETS_DIR.join("objects").join(dir_name);
-        let path = dir.join(&object.hash);
+/// Downloads the log config of `client` unless a valid copy is already there,
+/// for the instances installed before it was.
+pub(crate) async fn download_log_config(client: &Client) -> Result<(), DownloadError> {
+    if let (Some(config), Some(path)) = (client.logging_config(), log_config_path(client)) {
+        download_and_verify(&config.file.download, &path).await?;
+    }
+    Ok(())
+}
 
-        if path.exists() {
-            return Ok(());
-        }
+/// Where the log config of `client` goes, like the official launcher.
+pub(crate) fn log_config_path(client: &Client) -> Option<PathBuf> {
+    let config = client.logging_config()?;
+    Some(ASSETS_DIR.join("log_configs").join(&config.file.id))
+}
 
-        fs::create_dir_all(&dir)?;
-        let data = utils::download::get_as_bytes(&format!(
-            "https://resources.download.minecraft.net/{dir_name}/{}",
-            object.hash
-        ))
-        .await?;
+fn download_size(download: &Download) -> u64 {
+    download.size.map_or(0, |size| size as u64)
+}
 
-        fs::write(path, data)?;
-        Ok(())
-    };
+/// Name of the file in the natives directory recording the hash of every extracted natives jar.
+const NATIVES_HASHES: &str = ".hashes.json";
 
-    let iter = objects.into_iter();
-    let iter = iter.map(|(_, object)| object);
-    let outputs = download_futures(iter, 20, download_object).await;
-    for (i, output) in outputs.into_iter().enumerate() {
-        if let Err(err) = output {
-            println!("Failed to download object indexed {i}: {err:?}");
-            return Err(err);
+/// Extracts the natives jars of legacy versions into `natives_dir`, skipping
+/// the jars that didn't change since they were last extracted.
+pub fn extract_natives(
+    natives: Vec<(&Library, &Download, Bytes)>,
+    natives_dir: &Path,
+    progress: &ProgressReporter,
+) -> Result<(), BackendError> {
+    let bytes_total = natives.iter().map(|(_, _, bytes)| bytes.len() as u64).sum();
+    let progress = progress.phase(InstallPhase::Natives, natives.len(), bytes_total);
+
+    let hashes_path = natives_dir.join(NATIVES_HASHES);
+    let mut hashes: HashMap<String, String> = fs::read(&hashes_path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+
+    for (lib, native, bytes) in natives {
+        let hash = sha1_hex(&bytes);
+        let jar = native
+            .path
+            .as_ref()
+            .map_or_else(|| natives_dir.to_path_buf(), |path| LIBS_DIR.join(path));
+
+        if hashes.get(&native.url) != Some(&hash) {
+            let exclude = lib
+                .extract
+                .as_ref()
+                .and_then(|extract| extract.exclude.as_deref())
+                .unwrap_or_default();
+            let paths = exclude.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+
+            ZipExtractor::new(&bytes)
+                .exclude(&paths)
+                .extract(natives_dir)?;
+            hashes.insert(native.url.clone(), hash);
         }
+
+        progress.file_done(&jar, bytes.len() as u64);
     }
 
-    println!("Downloaded assets for {}", id);
+    fs::create_dir_all(natives_dir)?;
+    fs::write(hashes_path, serde_json::to_string_pretty(&hashes)?)?;
+
+    progress.finish();
     Ok(())
 }
 
-async fn install_libs(
-    path: PathBuf
-) -> Result<(), BackendError> {
-    println!("Downloading libraries...");
+/// Downloads a library, returning its natives jar if it has one for this platform.
+async fn download_lib<'a>(
+    lib: &'a Library,
+    progress: &PhaseProgress,
+) -> Result<Option<(&'a Library, &'a Download, Bytes)>, BackendError> {
+    if let Some(ref artifact) = lib.downloads.artifact {
+        download_to(artifact, &LIBS_DIR, progress).await?;
+    }
 
-    let download_lib = async |lib: &Library| -> Result<(), BackendError> {
-        if let Some(ref artifact) = lib.downloads.artifact {
-            download_to(artifact, &LIBS_DIR).await?;
+    match lib.native_from_platform() {
+        Some(native) => {
+            let bytes = download_and_read_file(native, &LIBS_DIR, progress).await?;
+            Ok(Some((lib, native, bytes)))
         }
+        None => Ok(None),
+    }
+}
 
-        // !!! This needs to be fixed!!!
-        // if let Some(native) = lib.native_from_platform() {
-        //     let bytes = download_and_read_file(native, &LIBS_DIR).await?;
+/// Installs a resolved client into an instance. Everything the install needs is passed
+/// in here rather than through globals, so several instances can install at once.
+#[derive(Debug)]
+pub struct ClientInstaller<'a> {
+    client: &'a Client,
+    /// The instance directory
+    path: &'a Path,
+    progress: &'a ProgressReporter,
+}
 
-        //     if let Some(ref extract_rules) = lib.extract {
-        //         let natives_dir = path.join(".natives");
+impl<'a> ClientInstaller<'a> {
+    pub fn new(client: &'a Client, path: &'a Path, progress: &'a ProgressReporter) -> Self {
+        Self {
+            client,
+            path,
+            progress,
+        }
+    }
+
+    /// Downloads the assets and libraries, skipping the files already there and valid.
+    pub async fn install(&self) -> Result<(), BackendError> {
+        self.install_assets().await?;
+        self.install_libs().await
+    }
 
-        //         let exclude = extract_rules.exclude.as_deref().unwrap_or_default();
-        //         let paths = exclude.iter().map(PathBuf::as_path).collect::<Vec<_>>();
-        //         let zip = ZipExtractor::new(&bytes).exclude(&paths);
+    async fn install_assets(&self) -> Result<(), BackendError> {
+        let index_path = asset_index_path(self.client);
+        let index_size = download_and_verify(&self.client.asset_index, &index_path).await?;
+        let index: AssetIndex = serde_json::from_slice(&fs::read(&index_path)?).unwrap();
+        let objects = index.objects.values().collect::<Vec<_>>();
+
+        let log_config = self.client.logging_config();
+        let files_total = objects.len() + 1 + log_config.iter().len();
+        let bytes_total = download_size(&self.client.asset_index)
+            + objects.iter().map(|object| object.size as u64).sum::<u64>()
+            + log_config.map_or(0, |config| download_size(&config.file.download));
+        let phase = self
+            .progress
+            .phase(InstallPhase::Assets, files_total, bytes_total);
+        phase.file_done(&index_path, index_size);
+
+        if let (Some(config), Some(path)) = (log_config, log_config_path(self.client)) {
+            let size = download_and_verify(&config.file.download, &path).await?;
+            phase.file_done(&path, size);
+        }
 
-        //         zip.extract(&natives_dir)?;
-        //     }
-        // }
+        let progress = &phase;
+        let outputs = download_futures(objects.iter(), |object| async move {
+            let path = object_path(object);
+            let size = download_and_verify(&object_download(object), &path).await?;
+            progress.file_done(&path, size);
+            Ok::<_, DownloadError>(())
+        })
+        .await;
+        for output in outputs {
+            output?;
+        }
+
+        if let Some(dir) = named_assets_dir(self.client, &index, self.path) {
+            for (name, object) in &index.objects {
+                let path = named_asset_path(&dir, name)
+                    .ok_or_else(|| InstallationError::InvalidAssetName(name.clone()))?;
+                copy_object(object, &path).await?;
+            }
+        }
+
+        phase.finish();
         Ok(())
+    }
+
+    /// Downloads the libraries and the client jar, then extracts the natives.
+    async fn install_libs(&self) -> Result<(), BackendError> {
+        let downloads = self
+            .client
+            .libraries()
+            .flat_map(|lib| {
+                lib.downloads
+                    .artifact
+                    .iter()
+                    .chain(lib.native_from_platform())
+            })
+            .chain([&self.client.downloads.client])
+            .collect::<Vec<_>>();
+        let bytes_total = downloads
+            .iter()
+            .map(|download| download_size(download))
+            .sum();
+        let phase = self
+            .progress
+            .phase(InstallPhase::Libraries, downloads.len(), bytes_total);
+
+        let outputs =
+            download_futures(self.client.libraries(), |lib| download_lib(lib, &phase)).await;
+
+        let mut natives = Vec::new();
+        for output in outputs {
+            if let Some(native) = output? {
+                natives.push(native);
+            }
+        }
+
+        download_to(
+            &self.client.downloads.client,
+            &self.path.join("client.jar"),
+            &phase,
+        )
+        .await?;
+        phase.finish();
+
+        extract_natives(natives, &self.path.join(".natives"), self.progress)
+    }
+}
+
+/// A file the client needs, with where it goes and where it comes from.
+#[derive(Debug, Clone)]
+pub struct ClientFile {
+    pub path: PathBuf,
+    pub download: Download,
+}
+
+/// Every file `client` needs in the instance at `path`.
+/// The asset objects are only listed if the asset index is valid.
+fn client_files(client: &Client, path: &Path) -> Vec<ClientFile> {
+    let mut files = vec![ClientFile {
+        path: path.join("client.jar"),
+        download: client.downloads.client.clone(),
+    }];
+
+    let libs = client.libraries().flat_map(|lib| {
+        lib.downloads
+            .artifact
+            .iter()
+            .chain(lib.native_from_platform())
+    });
+    for download in libs {
+        if let Some(ref lib_path) = download.path {
+            files.push(ClientFile {
+                path: LIBS_DIR.join(lib_path),
+                download: download.clone(),
+            });
+        }
+    }
+
+    if let (Some(config), Some(path)) = (client.logging_config(), log_config_path(client)) {
+        files.push(ClientFile {
+            path,
+            download: config.file.download.clone(),
+        });
+    }
+
+    files.push(ClientFile {
+        path: asset_index_path(client),
+        download: client.asset_index.clone(),
+    });
+
+    let Some(index) = read_asset_index(client) else {
+        return files;
     };
+    let named_dir = named_assets_dir(client, &index, path);
+
+    for (name, object) in &index.objects {
+        files.push(ClientFile {
+            path: object_path(object),
+            download: object_download(object),
+        });
+
+        // Repaired by downloading them there again, like the hashed ones.
+        // Installing refuses the names leading out of the directory
+        if let Some(path) = named_dir
+            .as_deref()
+            .and_then(|dir| named_asset_path(dir, name))
+        {
+            files.push(ClientFile {
+                path,
+                download: object_download(object),
+            });
+        }
+    }
+
+    files
+}
 
-    let outputs = download_futures((*TEMP_CLIENT).lock().await.as_ref().unwrap().libraries(), 5, download_lib).await;
-    for (i, output) in outputs.into_iter().enumerate() {
-        if let Err(err) = output {
-            println!("Failed to download library indexed {i}: {err:?}");
-            return Err(err);
+/// Files that failed verification.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct VerifyReport {
+    pub missing: Vec<PathBuf>,
+    pub corrupted: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty()
+    }
+}
+
+fn verify_files(files: &[ClientFile]) -> (VerifyReport, Vec<&ClientFile>) {
+    let mut report = VerifyReport::default();
+    let mut invalid = Vec::new();
+
+    for file in files {
+        match check_file(&file.download, &file.path) {
+            FileStatus::Valid => continue,
+            FileStatus::Missing => report.missing.push(file.path.clone()),
+            FileStatus::Corrupted => report.corrupted.push(file.path.clone()),
         }
+        invalid.push(file);
     }
 
-    println!("Done downloading libraries");
-    Ok(())
+    (report, invalid)
 }
 
-pub async fn install_client(
-    path: PathBuf,
-) -> Result<(), BackendError> {
-    install_assets().await?;
-    install_libs(path.clone()).await?;
+/// Checks the size and SHA-1 of every file `client` needs in the instance at `path`.
+pub fn verify_client(client: &Client, path: &Path) -> VerifyReport {
+    verify_files(&client_files(client, path)).0
+}
 
-    let client_path = path.join("client.jar");
+/// Downloads again the missing and corrupted files `client` needs in the instance
+/// at `path`, returning what was repaired.
+pub async fn repair_client(client: &Client, path: &Path) -> Result<VerifyReport, BackendError> {
+    // Objects are listed from the asset index, so it has to be repaired first
+    let index_path = asset_index_path(client);
+    let index_status = check_file(&client.asset_index, &index_path);
+    download_and_verify(&client.asset_index, &index_path).await?;
+
+    let files = client_files(client, path);
+    let (mut report, invalid) = verify_files(&files);
+
+    let outputs = download_futures(invalid.into_iter(), |file| {
+        download_and_verify(&file.download, &file.path)
+    })
+    .await;
+    for output in outputs {
+        output?;
+    }
 
-    download_to(&(*TEMP_CLIENT).lock().await.take().unwrap().downloads.client, &client_path).await?;
+    match index_status {
+        FileStatus::Valid => {}
+        FileStatus::Missing => report.missing.push(index_path),
+        FileStatus::Corrupted => report.corrupted.push(index_path),
+    }
 
-    Ok(())
+    Ok(report)
 }
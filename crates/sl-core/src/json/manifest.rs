@@ -1,40 +1,108 @@
-use std::fs;
+use std::{
+    fs,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use bytes::Bytes;
-use sl_meta::json::version_manifest::VersionManifest;
+use serde::{Deserialize, Serialize};
+use sl_meta::json::version_manifest::{Version, VersionManifest};
 use sl_utils::utils::{
     self,
+    download::{Downloader, Validators},
     errors::{BackendError, InstallationError},
+    http,
 };
 
-use crate::{MANIFEST, MANIFEST_PATH};
+use crate::{MANIFEST, MANIFEST_CACHE_PATH, MANIFEST_PATH};
+
+const MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// How long the cached manifest is used before asking whether it changed.
+const MANIFEST_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// When the cached manifest was last checked, and what identifies its version.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ManifestCache {
+    checked_at: u64,
+    #[serde(flatten)]
+    validators: Validators,
+}
 
-pub async fn fetch_version_manifest() {
-    let res = utils::download::get_as_bytes(
-        "https://launchermeta.mojang.com/mc/game/version_manifest_v2.json",
-    )
-    .await;
+impl ManifestCache {
+    fn read() -> Self {
+        fs::read(MANIFEST_CACHE_PATH.as_path())
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
 
-    if let Ok(res) = res {
-        fs::write(&MANIFEST_PATH.as_path(), res)
-            .expect("Failed writing into the file: version_manifest.json");
+    fn is_stale(&self) -> bool {
+        now().saturating_sub(self.checked_at) >= MANIFEST_MAX_AGE.as_secs()
     }
 }
 
-pub fn manifest_read() -> VersionManifest {
-    let buffer = fs::read_to_string(MANIFEST_PATH.as_path())
-        .expect("Failed reading the file: version_manifest.json");
-    serde_json::from_str(buffer.as_str()).expect("Failed to parse file: version_manifest.json")
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
 }
 
-pub async fn download_version(version: &str) -> Result<Bytes, BackendError> {
-    let Some(version) = MANIFEST.versions().find(|x| x.id == version) else {
-        // TODO: Use a different type for version instead of String
-        return Err(BackendError::InstallationError(
-            InstallationError::VersionNotFound(version.to_string()),
-        ));
+/// Refreshes the cached version manifest once it's stale, only downloading it again if it changed.
+/// Keeps the cached one if there's one and it can't be refreshed, e.g. offline.
+pub async fn fetch_version_manifest() -> Result<(), BackendError> {
+    let cached = MANIFEST_PATH.exists();
+    let mut cache = if cached {
+        ManifestCache::read()
+    } else {
+        ManifestCache::default()
     };
 
+    if cached && (http::is_offline() || !cache.is_stale()) {
+        return Ok(());
+    }
+
+    match Downloader::global()
+        .get_if_modified(MANIFEST_URL, &cache.validators)
+        .await
+    {
+        Ok(Some((data, validators))) => {
+            serde_json::from_slice::<VersionManifest>(&data)?;
+            fs::write(MANIFEST_PATH.as_path(), data)?;
+            cache.validators = validators;
+        }
+        Ok(None) => {}
+        Err(_) if cached => return Ok(()),
+        Err(err) => return Err(err.into()),
+    }
+
+    cache.checked_at = now();
+    fs::write(
+        MANIFEST_CACHE_PATH.as_path(),
+        serde_json::to_string_pretty(&cache)?,
+    )?;
+    Ok(())
+}
+
+pub fn manifest_read() -> Result<VersionManifest, BackendError> {
+    let buffer = fs::read(MANIFEST_PATH.as_path())?;
+    Ok(serde_json::from_slice(&buffer)?)
+}
+
+/// The version manifest, or [`InstallationError::ManifestUnavailable`] if it was never downloaded.
+pub fn manifest() -> Result<&'static VersionManifest, InstallationError> {
+    MANIFEST
+        .as_ref()
+        .ok_or(InstallationError::ManifestUnavailable)
+}
+
+pub fn manifest_version(version: &str) -> Result<&'static Version, BackendError> {
+    manifest()?.versions().find(|x| x.id == version).ok_or_else(|| {
+        // TODO: Use a different type for version instead of String
+        BackendError::InstallationError(InstallationError::VersionNotFound(version.to_string()))
+    })
+}
+
+pub async fn download_version(version: &Version) -> Result<Bytes, BackendError> {
     let res = utils::download::get_as_bytes(&version.url).await?;
     Ok(res)
 }
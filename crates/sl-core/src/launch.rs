@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::config::Config;
+
+/// Window size Minecraft uses when only one of the width and height is set.
+const DEFAULT_RESOLUTION: (u32, u32) = (854, 480);
+
+const DEFAULT_SERVER_PORT: &str = "25565";
+
+/// How the game starts, stored in the instance config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchSettings {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: bool,
+    pub demo: bool,
+    /// Vanilla can't start maximized, the window is given the size of the screen instead when it's known
+    pub maximized: bool,
+}
+
+impl LaunchSettings {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        let number = |key| config.get(key).and_then(|value| value.parse().ok());
+        let flag = |key| config.get(key) == Some("true");
+
+        Self {
+            width: number("resolution_width"),
+            height: number("resolution_height"),
+            fullscreen: flag("fullscreen"),
+            demo: flag("demo"),
+            maximized: flag("maximized"),
+        }
+    }
+
+    pub(crate) fn write_config(&self, config: &mut Config) {
+        for (key, value) in [
+            ("resolution_width", self.width),
+            ("resolution_height", self.height),
+        ] {
+            match value {
+                Some(value) => config.set(key, &value.to_string()),
+                None => {
+                    config.remove(key);
+                }
+            }
+        }
+
+        config.set("fullscreen", &self.fullscreen.to_string());
+        config.set("demo", &self.demo.to_string());
+        config.set("maximized", &self.maximized.to_string());
+    }
+
+    /// The size the window opens at, `None` to leave it to the game.
+    pub fn resolution(&self, options: &LaunchOptions) -> Option<(u32, u32)> {
+        if self.maximized {
+            if let Some(screen_size) = options.screen_size {
+                return Some(screen_size);
+            }
+        }
+
+        match (self.width, self.height) {
+            (None, None) => None,
+            (width, height) => Some((
+                width.unwrap_or(DEFAULT_RESOLUTION.0),
+                height.unwrap_or(DEFAULT_RESOLUTION.1),
+            )),
+        }
+    }
+
+    /// The game arguments no version JSON has, and the ones legacy versions have no rules for.
+    pub(crate) fn game_arguments(&self, options: &LaunchOptions, legacy: bool) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if legacy {
+            if self.demo {
+                args.push("--demo");
+            }
+            if self.resolution(options).is_some() {
+                args.extend([
+                    "--width",
+                    "${resolution_width}",
+                    "--height",
+                    "${resolution_height}",
+                ]);
+            }
+        }
+
+        if self.fullscreen {
+            args.push("--fullscreen");
+        }
+
+        args.into_iter().map(str::to_owned).collect()
+    }
+}
+
+/// Where the game goes once started, instead of the title screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "target", rename_all = "snake_case")]
+pub enum QuickPlay {
+    /// The name of the world's folder in `saves`
+    Singleplayer(String),
+    /// `host`, `host:port` or `[ipv6]:port`
+    Multiplayer(String),
+    /// The id of the realm
+    Realms(String),
+}
+
+impl QuickPlay {
+    /// What the `${quickPlaySingleplayer}`, `${quickPlayMultiplayer}` or `${quickPlayRealms}`
+    /// argument of 1.20 and newer stands for.
+    pub(crate) fn target(&self) -> &str {
+        match self {
+            QuickPlay::Singleplayer(target)
+            | QuickPlay::Multiplayer(target)
+            | QuickPlay::Realms(target) => target,
+        }
+    }
+
+    /// `--server` and `--port` for versions before Quick Play, which can only join servers.
+    pub(crate) fn legacy_arguments(&self) -> Option<Vec<String>> {
+        let QuickPlay::Multiplayer(address) = self else {
+            return None;
+        };
+
+        let (host, port) = split_address(address);
+        let port = port.unwrap_or(DEFAULT_SERVER_PORT);
+
+        Some(
+            ["--server", host, "--port", port]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        )
+    }
+}
+
+/// Splits `host:port` and `[ipv6]:port`, a bare IPv6 address like `::1` is all host.
+fn split_address(address: &str) -> (&str, Option<&str>) {
+    let is_port = |port: &str| port.parse::<u16>().is_ok();
+
+    if let Some((host, rest)) = address
+        .strip_prefix('[')
+        .and_then(|address| address.split_once(']'))
+    {
+        return match rest.strip_prefix(':') {
+            Some(port) if is_port(port) => (host, Some(port)),
+            _ if rest.is_empty() => (host, None),
+            _ => (address, None),
+        };
+    }
+
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && !host.contains(':') && is_port(port) => {
+            (host, Some(port))
+        }
+        _ => (address, None),
+    }
+}
+
+/// What only applies to one launch.
+#[derive(Debug, Default, Clone)]
+pub struct LaunchOptions {
+    /// Size of the screen the game opens on, see [`LaunchSettings::maximized`]
+    pub screen_size: Option<(u32, u32)>,
+    pub quick_play: Option<QuickPlay>,
+}
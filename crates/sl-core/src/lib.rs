@@ -5,15 +5,13 @@ use config::config_launcher_dir;
 use json::manifest::manifest_read;
 use sl_meta::json::{Arch, OsName};
 use sl_meta::json::version_manifest::VersionManifest;
-use sl_meta::json::vanilla::Client;
-use once_cell::sync::Lazy;
-use tokio::sync::Mutex;
-use std::sync::Arc;
 
 pub mod auth;
 pub mod config;
 pub mod installations;
 pub mod json;
+pub mod launch;
+pub mod progress;
 
 pub const MULTI_PATH_SEPARATOR: &'static str = if cfg!(target_os = "windows") {
     ";"
@@ -37,6 +35,10 @@ pub const ARCH: Arch = if cfg!(target_arch = "x86") {
     Arch::X86_64
 } else if cfg!(target_arch = "aarch64") {
     Arch::ARM64
+} else if cfg!(target_arch = "arm") {
+    Arch::ARM32
+} else if cfg!(target_arch = "riscv64") {
+    Arch::RiscV64
 } else {
     panic!("Unsupported Arch")
 };
@@ -49,9 +51,11 @@ lazy_static! {
     pub static ref LIBS_DIR: PathBuf = LAUNCHER_DIR.join("libs");
     pub static ref INSTALLATIONS_DIR: PathBuf = LAUNCHER_DIR.join("installations");
     pub static ref INSTALLATIONS_PATH: PathBuf = LAUNCHER_DIR.join("installations.json");
+    pub static ref ACCOUNTS_PATH: PathBuf = LAUNCHER_DIR.join("accounts.json");
+    pub static ref AUTHLIB_INJECTOR_PATH: PathBuf = LIBS_DIR.join("authlib-injector.jar");
+    pub static ref MIRRORS_PATH: PathBuf = LAUNCHER_DIR.join("mirrors.json");
     pub static ref MANIFEST_PATH: PathBuf = LAUNCHER_DIR.join("version_manifest.json");
-    pub static ref MANIFEST: VersionManifest = manifest_read();
-    pub static ref TEMP_CLIENT: Lazy<Arc<Mutex<Option<Client>>>> = Lazy::new(|| {
-        Arc::new(Mutex::new(None))
-    });
+    pub static ref MANIFEST_CACHE_PATH: PathBuf = LAUNCHER_DIR.join("version_manifest.cache.json");
+    /// `None` if the manifest was never downloaded, see [`json::manifest::manifest`]
+    pub static ref MANIFEST: Option<VersionManifest> = manifest_read().ok();
 }
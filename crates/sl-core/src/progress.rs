@@ -0,0 +1,156 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallPhase {
+    /// Looking up the version in the version manifest
+    Manifest,
+    ClientJson,
+    /// The asset index and objects
+    Assets,
+    /// The library jars, natives jars and the client jar
+    Libraries,
+    /// Extracting the natives jars of legacy versions
+    Natives,
+    Java,
+    /// The mod loader profile, e.g. Fabric
+    Loader,
+}
+
+impl fmt::Display for InstallPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InstallPhase::Manifest => "Version manifest",
+            InstallPhase::ClientJson => "Client JSON",
+            InstallPhase::Assets => "Assets",
+            InstallPhase::Libraries => "Libraries",
+            InstallPhase::Natives => "Natives",
+            InstallPhase::Java => "Java",
+            InstallPhase::Loader => "Loader",
+        })
+    }
+}
+
+/// How far a phase got, totals are 0 when they aren't known.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InstallEvent {
+    PhaseStarted {
+        phase: InstallPhase,
+        progress: Progress,
+    },
+    /// A file was downloaded, or was already there and valid
+    FileDone {
+        phase: InstallPhase,
+        path: PathBuf,
+        size: u64,
+        progress: Progress,
+    },
+    PhaseFinished {
+        phase: InstallPhase,
+        progress: Progress,
+    },
+}
+
+/// Where install events go, either a callback or a channel. The default reports nothing.
+#[derive(Clone, Default)]
+pub struct ProgressReporter(Option<Arc<dyn Fn(InstallEvent) + Send + Sync>>);
+
+impl ProgressReporter {
+    pub fn new(on_event: impl Fn(InstallEvent) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(on_event)))
+    }
+
+    /// A reporter sending its events to the returned receiver.
+    pub fn channel() -> (Self, UnboundedReceiver<InstallEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let reporter = Self::new(move |event| {
+            let _ = sender.send(event);
+        });
+
+        (reporter, receiver)
+    }
+
+    fn emit(&self, event: InstallEvent) {
+        if let Some(ref on_event) = self.0 {
+            on_event(event);
+        }
+    }
+
+    /// Starts reporting `phase`, expecting `files_total` files weighing `bytes_total`.
+    pub(crate) fn phase(
+        &self,
+        phase: InstallPhase,
+        files_total: usize,
+        bytes_total: u64,
+    ) -> PhaseProgress {
+        let progress = Progress {
+            files_total,
+            bytes_total,
+            ..Default::default()
+        };
+        self.emit(InstallEvent::PhaseStarted { phase, progress });
+
+        PhaseProgress {
+            reporter: self.clone(),
+            phase,
+            progress: Mutex::new(progress),
+        }
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ProgressReporter")
+            .field(&self.0.is_some())
+            .finish()
+    }
+}
+
+/// A phase being reported, shared by the downloads running concurrently in it.
+pub(crate) struct PhaseProgress {
+    reporter: ProgressReporter,
+    phase: InstallPhase,
+    progress: Mutex<Progress>,
+}
+
+impl PhaseProgress {
+    pub(crate) fn file_done(&self, path: &Path, size: u64) {
+        let progress = {
+            let mut progress = self.progress.lock().unwrap();
+            progress.files_done += 1;
+            progress.bytes_done += size;
+            *progress
+        };
+
+        self.reporter.emit(InstallEvent::FileDone {
+            phase: self.phase,
+            path: path.to_path_buf(),
+            size,
+            progress,
+        });
+    }
+
+    pub(crate) fn finish(self) {
+        self.reporter.emit(InstallEvent::PhaseFinished {
+            phase: self.phase,
+            progress: self.progress.into_inner().unwrap(),
+        });
+    }
+}
@@ -0,0 +1,35 @@
+use std::fs;
+
+use sl_core::auth::{
+    accounts::Accounts,
+    secrets::{FileSecretBackend, KeySource, SecretBackend},
+};
+use tempfile::TempDir;
+
+fn backend(dir: &TempDir) -> FileSecretBackend {
+    FileSecretBackend::open(
+        dir.path().join("secrets.json"),
+        KeySource::Passphrase("hunter2".to_string()),
+    )
+    .unwrap()
+}
+
+#[test]
+fn malformed_accounts_keep_their_secrets() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("accounts.json");
+    let secrets = backend(&dir);
+    secrets.set("microsoft:abc", "tokens").unwrap();
+
+    fs::write(
+        &path,
+        r#"{"active": "abc", "accounts": [{"type": "microsoft""#,
+    )
+    .unwrap();
+    assert!(Accounts::load_with(&path, &secrets).is_err());
+
+    assert_eq!(
+        secrets.get("microsoft:abc").unwrap().as_deref(),
+        Some("tokens")
+    );
+}
@@ -0,0 +1,110 @@
+use std::{fs, path::Path};
+
+use serde_json::{json, Value};
+use sl_core::{
+    json::client::{game_assets_dir, verify_client, ClientInstaller},
+    progress::ProgressReporter,
+    ASSETS_DIR,
+};
+use sl_meta::json::vanilla::Client;
+use sl_utils::utils::download::sha1_hex;
+use tempfile::TempDir;
+
+const OBJECT: &[u8] = b"sound";
+
+/// A valid copy of `data`, so installing never reaches the network.
+fn file(path: &Path, data: &[u8]) -> Value {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, data).unwrap();
+    json!({ "url": "http://127.0.0.1:9/file", "sha1": sha1_hex(data), "size": data.len() })
+}
+
+/// A legacy client whose `assets` index holds [`OBJECT`] under `name`.
+fn client(instance: &Path, assets: &str, index: Value, name: &str) -> Client {
+    let hash = sha1_hex(OBJECT);
+    file(
+        &ASSETS_DIR.join("objects").join(&hash[..2]).join(&hash),
+        OBJECT,
+    );
+
+    let mut index = index;
+    index["objects"] = json!({ name: { "hash": hash, "size": OBJECT.len() } });
+    let index = serde_json::to_vec(&index).unwrap();
+
+    serde_json::from_value(json!({
+        "minecraftArguments": "--gameDir ${game_directory} --assetsDir ${game_assets}",
+        "libraries": [],
+        "mainClass": "net.minecraft.client.Minecraft",
+        "downloads": { "client": file(&instance.join("client.jar"), b"client") },
+        "assets": assets,
+        "assetIndex": file(&ASSETS_DIR.join("indexes").join(format!("{assets}.json")), &index),
+        "id": "1.5.2",
+        "releaseTime": "2013-04-25T15:45:00+00:00",
+        "type": "release"
+    }))
+    .unwrap()
+}
+
+// In its own test binary, as the launcher directory is read once for the whole process
+#[tokio::test]
+async fn named_assets_layout() {
+    let home = TempDir::new().unwrap();
+    std::env::set_var("HOME", home.path());
+    std::env::set_var("APPDATA", home.path());
+
+    let instance = TempDir::new().unwrap();
+    let instance = instance.path();
+    let progress = ProgressReporter::default();
+
+    let hashed = client(instance, "1.12", json!({}), "minecraft/sounds/a.ogg");
+    ClientInstaller::new(&hashed, instance, &progress)
+        .install()
+        .await
+        .unwrap();
+    assert_eq!(game_assets_dir(&hashed, instance), *ASSETS_DIR);
+
+    let legacy = client(
+        instance,
+        "legacy",
+        json!({ "virtual": true }),
+        "sounds/a.ogg",
+    );
+    ClientInstaller::new(&legacy, instance, &progress)
+        .install()
+        .await
+        .unwrap();
+    let virtual_dir = ASSETS_DIR.join("virtual").join("legacy");
+    assert_eq!(game_assets_dir(&legacy, instance), virtual_dir);
+    assert_eq!(fs::read(virtual_dir.join("sounds/a.ogg")).unwrap(), OBJECT);
+
+    let pre_1_6 = client(
+        instance,
+        "pre-1.6",
+        json!({ "map_to_resources": true }),
+        "sounds/a.ogg",
+    );
+    ClientInstaller::new(&pre_1_6, instance, &progress)
+        .install()
+        .await
+        .unwrap();
+    let resources = instance.join("resources");
+    assert_eq!(game_assets_dir(&pre_1_6, instance), resources);
+    assert_eq!(fs::read(resources.join("sounds/a.ogg")).unwrap(), OBJECT);
+    assert!(verify_client(&pre_1_6, instance).is_ok());
+
+    // Names leading out of the resources are refused, and never listed for a repair
+    for name in ["../escaped.ogg", "/tmp/escaped.ogg"] {
+        let escaping = client(
+            instance,
+            "escaping",
+            json!({ "map_to_resources": true }),
+            name,
+        );
+        assert!(ClientInstaller::new(&escaping, instance, &progress)
+            .install()
+            .await
+            .is_err());
+        assert!(!instance.join("escaped.ogg").exists());
+        assert!(verify_client(&escaping, instance).is_ok());
+    }
+}
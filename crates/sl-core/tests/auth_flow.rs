@@ -0,0 +1,597 @@
+use serde_json::{json, Value};
+use sl_core::auth::{
+    AuthEndpoints, AuthFlow, AuthServiceError, AuthTokenResponse, CancellationToken, LoginEvent,
+    PlayerProfile,
+};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use wiremock::{
+    matchers::{body_string_contains, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const CLIENT_ID: &str = "test-client";
+
+fn device_code_response() -> Value {
+    json!({
+        "user_code": "ABCD-EFGH",
+        "device_code": "device-code",
+        "verification_uri": "https://www.microsoft.com/link",
+        "expires_in": 900,
+        "interval": 0,
+        "message": "Enter the code"
+    })
+}
+
+fn token_response() -> Value {
+    json!({
+        "token_type": "Bearer",
+        "scope": "XboxLive.signin offline_access",
+        "expires_in": 3600,
+        "ext_expires_in": 3600,
+        "access_token": "microsoft-token",
+        "refresh_token": "refresh-token"
+    })
+}
+
+fn xbox_response(token: &str) -> Value {
+    json!({
+        "IssueInstant": "2024-01-01T00:00:00.0000000Z",
+        "NotAfter": "2024-01-15T00:00:00.0000000Z",
+        "Token": token,
+        "DisplayClaims": { "xui": [{ "uhs": "user-hash" }] }
+    })
+}
+
+fn oauth_error(error: &str) -> ResponseTemplate {
+    ResponseTemplate::new(400).set_body_json(json!({ "error": error }))
+}
+
+/// Mounts the happy path of every service, tests override single steps with
+/// higher priority mocks.
+async fn mock_services() -> MockServer {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/consumers/oauth2/v2.0/devicecode"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(device_code_response()))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/consumers/oauth2/v2.0/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(token_response()))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/authenticate"))
+        .and(body_string_contains("d=microsoft-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(xbox_response("xbl-token")))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xsts/authorize"))
+        .and(body_string_contains("xbl-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(xbox_response("xsts-token")))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/authentication/login_with_xbox"))
+        .and(body_string_contains("XBL3.0 x=user-hash;xsts-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "username": "some-uuid",
+            "roles": [],
+            "access_token": "minecraft-token",
+            "expires_in": 86400,
+            "token_type": "Bearer"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/entitlements/mcstore"))
+        .and(header("authorization", "Bearer minecraft-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [
+                { "name": "product_minecraft", "signature": "sig" },
+                { "name": "game_minecraft", "signature": "sig" }
+            ],
+            "signature": "sig",
+            "keyId": "1"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile"))
+        .and(header("authorization", "Bearer minecraft-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "069a79f444e94726a5befca90e38aaf5",
+            "name": "Notch",
+            "skins": [],
+            "capes": []
+        })))
+        .mount(&server)
+        .await;
+
+    server
+}
+
+fn flow(server: &MockServer) -> AuthFlow {
+    AuthFlow::with_endpoints(CLIENT_ID, AuthEndpoints::from_base(&server.uri()))
+}
+
+/// Overrides the token endpoint for the device code poll with `response`.
+async fn mount_poll_response(server: &MockServer, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path("/consumers/oauth2/v2.0/token"))
+        .and(body_string_contains("device_code"))
+        .respond_with(response)
+        .with_priority(1)
+        .mount(server)
+        .await;
+}
+
+async fn wait_for_login_error(response: ResponseTemplate) -> AuthServiceError {
+    let server = mock_services().await;
+    mount_poll_response(&server, response).await;
+
+    let mut auth = flow(&server);
+    auth.request_code().await.unwrap();
+    auth.wait_for_login().await.unwrap_err()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn full_device_code_chain() {
+    let server = mock_services().await;
+
+    // The user takes a moment before entering the code
+    Mock::given(method("POST"))
+        .and(path("/consumers/oauth2/v2.0/token"))
+        .respond_with(oauth_error("authorization_pending"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+
+    let code = auth.request_code().await.unwrap();
+    assert_eq!(code.user_code, "ABCD-EFGH");
+
+    let token = auth.wait_for_login().await.unwrap();
+    assert_eq!(token.refresh_token, "refresh-token");
+
+    let xbox = auth.login_in_xbox_live().await.unwrap();
+    assert_eq!(xbox.token, "xbl-token");
+
+    let minecraft = auth.login_in_minecraft().await.unwrap();
+    assert_eq!(minecraft.access_token, "minecraft-token");
+
+    let profile = PlayerProfile::with_endpoints(minecraft.access_token.clone(), auth.endpoints())
+        .await
+        .unwrap();
+    assert_eq!(profile.username, "Notch");
+    assert_eq!(profile.uuid, "069a79f444e94726a5befca90e38aaf5");
+    assert_eq!(profile.access_token, "minecraft-token");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_declined() {
+    let err = wait_for_login_error(oauth_error("authorization_declined")).await;
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_expired() {
+    let err = wait_for_login_error(oauth_error("expired_token")).await;
+    assert!(matches!(err, AuthServiceError::DeviceCodeExpired));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_slow_down() {
+    let server = mock_services().await;
+
+    Mock::given(method("POST"))
+        .and(path("/consumers/oauth2/v2.0/token"))
+        .respond_with(oauth_error("slow_down"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.request_code().await.unwrap();
+
+    let mut countdown = Vec::new();
+    let started = std::time::Instant::now();
+    auth.wait_for_login_with(&CancellationToken::new(), |event| {
+        if let LoginEvent::Waiting { remaining_secs } = event {
+            countdown.push(remaining_secs);
+        }
+    })
+    .await
+    .unwrap();
+
+    assert!(started.elapsed() >= std::time::Duration::from_secs(5));
+    // Once a second while the next poll is 5 seconds away
+    assert!(countdown.len() >= 5);
+    assert!(countdown.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert!(countdown[0] - countdown[countdown.len() - 1] >= 4);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_code_expires_locally() {
+    let server = mock_services().await;
+
+    let mut code = device_code_response();
+    code["expires_in"] = json!(0);
+    Mock::given(method("GET"))
+        .and(path("/consumers/oauth2/v2.0/devicecode"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(code))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.request_code().await.unwrap();
+
+    let err = auth.wait_for_login().await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::DeviceCodeExpired));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_cancelled() {
+    let server = mock_services().await;
+
+    let mut code = device_code_response();
+    code["interval"] = json!(60);
+    Mock::given(method("GET"))
+        .and(path("/consumers/oauth2/v2.0/devicecode"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(code))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.request_code().await.unwrap();
+
+    let cancel = CancellationToken::new();
+    let mut events = Vec::new();
+
+    let canceller = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        canceller.cancel();
+    });
+
+    let err = auth
+        .wait_for_login_with(&cancel, |event| events.push(event))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, AuthServiceError::Cancelled));
+    assert!(matches!(
+        events.as_slice(),
+        [LoginEvent::Waiting { remaining_secs }] if *remaining_secs > 800
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_invalid_grant() {
+    let err = wait_for_login_error(oauth_error("invalid_grant")).await;
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_unexpected_status() {
+    let err = wait_for_login_error(ResponseTemplate::new(500)).await;
+    assert!(matches!(err, AuthServiceError::UnknownError));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_login_malformed_error() {
+    let err = wait_for_login_error(ResponseTemplate::new(400).set_body_string("not json")).await;
+    assert!(matches!(err, AuthServiceError::Json(_)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refresh_login() {
+    let server = mock_services().await;
+    let mut auth = flow(&server);
+
+    let token = auth.refresh_login("refresh-token").await.unwrap();
+    assert_eq!(token.access_token, "microsoft-token");
+
+    auth.login_in_xbox_live().await.unwrap();
+    auth.login_in_minecraft().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refresh_login_rejected() {
+    let server = mock_services().await;
+
+    Mock::given(method("POST"))
+        .and(path("/consumers/oauth2/v2.0/token"))
+        .and(body_string_contains("grant_type=refresh_token"))
+        .respond_with(oauth_error("invalid_grant"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let err = flow(&server).refresh_login("revoked").await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
+
+async fn xsts_error(xerr: u64) -> AuthServiceError {
+    let server = mock_services().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xsts/authorize"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "Identity": "0",
+            "XErr": xerr,
+            "Message": "",
+            "Redirect": "https://start.ui.xboxlive.com/CreateAccount"
+        })))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.refresh_login("refresh-token").await.unwrap();
+    auth.login_in_xbox_live().await.unwrap();
+    auth.login_in_minecraft().await.unwrap_err()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn xsts_error_codes() {
+    assert!(matches!(
+        xsts_error(2148916233).await,
+        AuthServiceError::NoXboxProfile
+    ));
+    assert!(matches!(
+        xsts_error(2148916235).await,
+        AuthServiceError::XboxRegionBanned
+    ));
+    assert!(matches!(
+        xsts_error(2148916236).await,
+        AuthServiceError::AdultVerificationRequired
+    ));
+    assert!(matches!(
+        xsts_error(2148916238).await,
+        AuthServiceError::ChildAccount
+    ));
+    assert!(matches!(
+        xsts_error(1234).await,
+        AuthServiceError::Xsts(1234)
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn game_not_owned() {
+    let server = mock_services().await;
+
+    Mock::given(method("GET"))
+        .and(path("/entitlements/mcstore"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [],
+            "signature": "sig",
+            "keyId": "1"
+        })))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.refresh_login("refresh-token").await.unwrap();
+    auth.login_in_xbox_live().await.unwrap();
+
+    let err = auth.login_in_minecraft().await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::GameNotOwned));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn profile_errors() {
+    let server = mock_services().await;
+    let endpoints = AuthEndpoints::from_base(&server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile"))
+        .and(header("authorization", "Bearer no-profile"))
+        .respond_with(ResponseTemplate::new(404))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile"))
+        .and(header("authorization", "Bearer expired"))
+        .respond_with(ResponseTemplate::new(401))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let err = PlayerProfile::with_endpoints("no-profile".to_string(), &endpoints)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthServiceError::NoMinecraftProfile));
+
+    let err = PlayerProfile::with_endpoints("expired".to_string(), &endpoints)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
+
+/// Runs the browser login, answering the login page with a redirect carrying `query`
+/// (the `state` sent by the launcher is appended unless `query` already has one).
+async fn browser_login(
+    server: &MockServer,
+    query: &'static str,
+) -> Result<AuthTokenResponse, AuthServiceError> {
+    let mut auth = flow(server);
+
+    let token = auth
+        .browser_login_with(&CancellationToken::new(), |event| {
+            let LoginEvent::OpenBrowser { url } = event else {
+                return;
+            };
+
+            let url = reqwest::Url::parse(&url).unwrap();
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+                    .unwrap()
+            };
+
+            assert_eq!(url.path(), "/consumers/oauth2/v2.0/authorize");
+            assert_eq!(param("code_challenge_method"), "S256");
+            assert_eq!(param("code_challenge").len(), 43);
+
+            let mut redirect = format!("{}/?{query}", param("redirect_uri"));
+            if !query.contains("state=") {
+                redirect.push_str(&format!("&state={}", param("state")));
+            }
+
+            tokio::spawn(async move {
+                // A browser asking for the favicon first must not end the login
+                let base = redirect.split("/?").next().unwrap().to_string();
+                reqwest::get(format!("{base}/favicon.ico")).await.unwrap();
+                reqwest::get(redirect).await.unwrap();
+            });
+        })
+        .await?;
+
+    Ok(token.clone())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browser_login_exchanges_code() {
+    let server = mock_services().await;
+
+    Mock::given(method("POST"))
+        .and(path("/consumers/oauth2/v2.0/token"))
+        .and(body_string_contains("grant_type=authorization_code"))
+        .and(body_string_contains("code=auth-code"))
+        .and(body_string_contains("code_verifier="))
+        .and(body_string_contains(
+            "redirect_uri=http%3A%2F%2Flocalhost%3A",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "token_type": "Bearer",
+            "scope": "XboxLive.signin offline_access",
+            "expires_in": 3600,
+            "ext_expires_in": 3600,
+            "access_token": "microsoft-token",
+            "refresh_token": "browser-refresh-token"
+        })))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let token = browser_login(&server, "code=auth-code").await.unwrap();
+    assert_eq!(token.refresh_token, "browser-refresh-token");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browser_login_rejects_wrong_state() {
+    let server = mock_services().await;
+
+    let err = browser_login(&server, "code=auth-code&state=forged")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidRedirect));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browser_login_denied() {
+    let server = mock_services().await;
+
+    let err = browser_login(&server, "error=access_denied")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browser_login_ignores_stray_connections() {
+    // Nothing listens there, the login has to end before exchanging a code
+    let mut auth =
+        AuthFlow::with_endpoints(CLIENT_ID, AuthEndpoints::from_base("http://127.0.0.1:9"));
+
+    let result = auth
+        .browser_login_with(&CancellationToken::new(), |event| {
+            let LoginEvent::OpenBrowser { url } = event else {
+                return;
+            };
+
+            let url = reqwest::Url::parse(&url).unwrap();
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+                    .unwrap()
+            };
+            let redirect_uri = param("redirect_uri");
+            let state = param("state");
+            let port = reqwest::Url::parse(&redirect_uri).unwrap().port().unwrap();
+
+            tokio::spawn(async move {
+                // A preconnect closed without sending anything, one staying silent and a malformed request
+                drop(TcpStream::connect(("127.0.0.1", port)).await.unwrap());
+                let _silent = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+                let mut malformed = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+                malformed.write_all(b"garbage\r\n\r\n").await.unwrap();
+
+                reqwest::get(format!("{redirect_uri}/?error=access_denied&state={state}"))
+                    .await
+                    .unwrap();
+            });
+        })
+        .await;
+
+    assert!(matches!(result, Err(AuthServiceError::InvalidAccessToken)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn xbox_live_unavailable() {
+    let server = mock_services().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/authenticate"))
+        .respond_with(ResponseTemplate::new(503))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.refresh_login("refresh-token").await.unwrap();
+
+    let err = auth.login_in_xbox_live().await.unwrap_err();
+    assert!(matches!(
+        err,
+        AuthServiceError::UnexpectedStatus(status) if status == 503
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn minecraft_login_rate_limited() {
+    let server = mock_services().await;
+
+    Mock::given(method("POST"))
+        .and(path("/authentication/login_with_xbox"))
+        .respond_with(ResponseTemplate::new(429))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let mut auth = flow(&server);
+    auth.refresh_login("refresh-token").await.unwrap();
+    auth.login_in_xbox_live().await.unwrap();
+
+    let err = auth.login_in_minecraft().await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::RateLimited));
+}
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use sl_core::{
+    auth::secrets::{FileSecretBackend, KeySource},
+    config::config::{set_proxy_password, Config},
+    MULTI_PATH_SEPARATOR,
+};
+use sl_utils::utils::errors::ConfigError;
+use tempfile::TempDir;
+
+fn config(entries: &[(&str, &str)]) -> Config {
+    Config::new(
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>(),
+    )
+}
+
+#[test]
+fn http_config() {
+    let certificates = format!("/etc/ca.pem{MULTI_PATH_SEPARATOR}/etc/other.der");
+    let http = config(&[
+        ("proxy", "http://proxy.example.com:8080"),
+        ("proxy_username", "steve"),
+        ("no_proxy", "localhost,.example.org"),
+        ("root_certificates", &certificates),
+        ("connect_timeout", "10"),
+        ("user_agent", ""),
+    ])
+    .http_config()
+    .unwrap();
+
+    assert_eq!(http.proxy.as_deref(), Some("http://proxy.example.com:8080"));
+    assert_eq!(http.proxy_username.as_deref(), Some("steve"));
+    assert_eq!(http.proxy_password, None);
+    assert_eq!(http.no_proxy.as_deref(), Some("localhost,.example.org"));
+    assert_eq!(
+        http.root_certificates,
+        vec![
+            PathBuf::from("/etc/ca.pem"),
+            PathBuf::from("/etc/other.der")
+        ]
+    );
+    assert_eq!(http.connect_timeout, Some(Duration::from_secs(10)));
+    assert_eq!(http.read_timeout, None);
+    assert_eq!(http.user_agent, None);
+}
+
+#[test]
+fn invalid_timeout() {
+    let err = config(&[("read_timeout", "soon")])
+        .http_config()
+        .unwrap_err();
+    assert!(
+        matches!(err, ConfigError::InvalidValue(key, value) if key == "read_timeout" && value == "soon")
+    );
+}
+
+#[test]
+fn proxy_password_is_a_secret() {
+    let dir = TempDir::new().unwrap();
+    let secrets = FileSecretBackend::open(
+        dir.path().join("secrets.json"),
+        KeySource::Passphrase("hunter2".to_string()),
+    )
+    .unwrap();
+
+    // Left in the config by an older version
+    let mut legacy = config(&[("proxy_username", "steve"), ("proxy_password", "hunter2")]);
+    assert_eq!(legacy.http_config().unwrap().proxy_password, None);
+    assert_eq!(
+        legacy.proxy_password(&secrets).unwrap().as_deref(),
+        Some("hunter2")
+    );
+    assert_eq!(legacy.get("proxy_password"), None);
+
+    set_proxy_password(&secrets, Some("correct horse")).unwrap();
+    assert_eq!(
+        config(&[]).proxy_password(&secrets).unwrap().as_deref(),
+        Some("correct horse")
+    );
+
+    set_proxy_password(&secrets, None).unwrap();
+    assert_eq!(config(&[]).proxy_password(&secrets).unwrap(), None);
+}
@@ -0,0 +1,69 @@
+use std::{fs, future::Future, time::Duration};
+
+use sl_core::{
+    auth::accounts::Account, installations::Installation, json::client::download_and_verify,
+    launch::LaunchOptions, progress::ProgressReporter,
+};
+use sl_meta::json::vanilla::Download;
+use sl_utils::utils::download::sha1_hex;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn assert_send<F: Future + Send>(_: F) {}
+
+/// Installs and launches run as tasks spawned by the app, several at once.
+#[test]
+fn install_futures_are_send() {
+    let check = |mut installation: Installation,
+                 progress: ProgressReporter,
+                 account: Account,
+                 options: LaunchOptions| {
+        assert_send(async move {
+            installation.install(&progress).await?;
+            installation.install_fabric("0.16.14", &progress).await?;
+            installation.repair().await?;
+            installation.execute(&account, &options).await
+        });
+    };
+
+    // Never called, it only has to compile
+    let _ = check;
+}
+
+/// Two installs sharing a library download it once, the second finds the first's copy.
+#[tokio::test]
+async fn shared_files_are_downloaded_once() {
+    let server = MockServer::start().await;
+    let data = b"library contents".to_vec();
+
+    Mock::given(method("GET"))
+        .and(path("/library.jar"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_bytes(data.clone())
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let download = Download {
+        path: None,
+        url: format!("{}/library.jar", server.uri()),
+        sha1: Some(sha1_hex(&data)),
+        size: Some(data.len() as i32),
+    };
+    let dir = TempDir::new().unwrap();
+    let jar = dir.path().join("library.jar");
+
+    let (first, second) = tokio::join!(
+        download_and_verify(&download, &jar),
+        download_and_verify(&download, &jar)
+    );
+    assert_eq!(first.unwrap(), data.len() as u64);
+    assert_eq!(second.unwrap(), data.len() as u64);
+    assert_eq!(fs::read(&jar).unwrap(), data);
+}
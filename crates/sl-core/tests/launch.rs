@@ -0,0 +1,312 @@
+use serde_json::{json, Value};
+use sl_core::{
+    installations::{Installation, InstallationInfo},
+    launch::{LaunchOptions, LaunchSettings, QuickPlay},
+};
+use sl_meta::json::{vanilla::Client, version_manifest::VersionType};
+
+const SCREEN: LaunchOptions = LaunchOptions {
+    screen_size: Some((2560, 1440)),
+    quick_play: None,
+};
+
+#[test]
+fn resolution() {
+    let settings = |width, height, maximized| LaunchSettings {
+        width,
+        height,
+        maximized,
+        ..Default::default()
+    };
+    let no_screen = LaunchOptions::default();
+
+    assert_eq!(settings(None, None, false).resolution(&SCREEN), None);
+    assert_eq!(
+        settings(Some(1280), Some(720), false).resolution(&SCREEN),
+        Some((1280, 720))
+    );
+    // Minecraft's default for the missing one
+    assert_eq!(
+        settings(Some(1280), None, false).resolution(&no_screen),
+        Some((1280, 480))
+    );
+    assert_eq!(
+        settings(Some(1280), Some(720), true).resolution(&SCREEN),
+        Some((2560, 1440))
+    );
+    assert_eq!(
+        settings(Some(1280), Some(720), true).resolution(&no_screen),
+        Some((1280, 720))
+    );
+    assert_eq!(settings(None, None, true).resolution(&no_screen), None);
+}
+
+#[test]
+fn quick_play_from_the_app() {
+    let quick_play: QuickPlay =
+        serde_json::from_value(json!({ "kind": "multiplayer", "target": "localhost:25566" }))
+            .unwrap();
+
+    assert_eq!(
+        quick_play,
+        QuickPlay::Multiplayer("localhost:25566".to_owned())
+    );
+}
+
+/// A client.json with `arguments`, either the `minecraftArguments` string or the
+/// `arguments` object of 1.13 and newer.
+fn client(version: &str, release_time: &str, arguments: Value) -> Client {
+    let download = json!({ "url": "https://example.com/file", "sha1": null, "size": null });
+    serde_json::from_value(json!({
+        "arguments": arguments,
+        "libraries": [],
+        "mainClass": "net.minecraft.client.main.Main",
+        "downloads": { "client": download },
+        "assets": "1.12",
+        "assetIndex": download,
+        "id": version,
+        "releaseTime": release_time,
+        "type": "release",
+        "logging": {
+            "client": {
+                "argument": "-Dlog4j.configurationFile=${path}",
+                "file": {
+                    "id": "client-1.12.xml",
+                    "url": "https://example.com/client-1.12.xml",
+                    "sha1": null,
+                    "size": null
+                },
+                "type": "log4j2-xml"
+            }
+        }
+    }))
+    .unwrap()
+}
+
+fn installation(client: &Client) -> Installation {
+    Installation {
+        name: client.id.clone(),
+        info: InstallationInfo {
+            version: client.id.clone(),
+            release_time: client.release_time.clone(),
+            r#type: Some(VersionType::Release),
+        },
+    }
+}
+
+fn game_arguments(
+    client: &Client,
+    settings: &LaunchSettings,
+    options: &LaunchOptions,
+) -> Vec<String> {
+    installation(client)
+        .launch_arguments(client, settings, options)
+        .unwrap()
+        .1
+}
+
+fn modern_client() -> Client {
+    modern_client_of("2023-06-12T13:25:51+00:00")
+}
+
+fn modern_client_of(release_time: &str) -> Client {
+    let feature = |feature: &str, value: Value| {
+        json!({
+            "rules": [{ "action": "allow", "features": { feature: true } }],
+            "value": value
+        })
+    };
+
+    client(
+        "1.20.1",
+        release_time,
+        json!({
+            "game": [
+                "--username",
+                "${auth_player_name}",
+                feature("is_demo_user", json!("--demo")),
+                feature(
+                    "has_custom_resolution",
+                    json!(["--width", "${resolution_width}", "--height", "${resolution_height}"])
+                ),
+                feature(
+                    "is_quick_play_multiplayer",
+                    json!(["--quickPlayMultiplayer", "${quickPlayMultiplayer}"])
+                )
+            ],
+            "jvm": ["-cp", "${classpath}"]
+        }),
+    )
+}
+
+fn legacy_client() -> Client {
+    client(
+        "1.12.2",
+        "2017-09-18T08:39:46+00:00",
+        json!("--username ${auth_player_name} --version ${version_name}"),
+    )
+}
+
+#[test]
+fn launch_settings_arguments() {
+    let window = LaunchSettings {
+        width: Some(1280),
+        height: Some(720),
+        fullscreen: true,
+        demo: true,
+        ..Default::default()
+    };
+    let expected = [
+        "--demo",
+        "--width",
+        "${resolution_width}",
+        "--height",
+        "${resolution_height}",
+        "--fullscreen",
+    ];
+
+    for client in [modern_client(), legacy_client()] {
+        let args = game_arguments(&client, &LaunchSettings::default(), &SCREEN);
+        for arg in expected {
+            assert!(!args.iter().any(|a| a == arg), "{} has {arg}", client.id);
+        }
+
+        let args = game_arguments(&client, &window, &SCREEN);
+        for arg in expected {
+            assert!(args.iter().any(|a| a == arg), "{} lacks {arg}", client.id);
+        }
+    }
+
+    // Only a maximized window with a known screen gets a size
+    let maximized = LaunchSettings {
+        maximized: true,
+        ..Default::default()
+    };
+    let args = game_arguments(&modern_client(), &maximized, &LaunchOptions::default());
+    assert!(!args.iter().any(|a| a == "--width"));
+    let args = game_arguments(&modern_client(), &maximized, &SCREEN);
+    assert!(args.iter().any(|a| a == "--width"));
+}
+
+fn join_server(address: &str) -> LaunchOptions {
+    LaunchOptions {
+        quick_play: Some(QuickPlay::Multiplayer(address.to_owned())),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn legacy_server_address() {
+    let server_args = |address| {
+        let args = game_arguments(
+            &legacy_client(),
+            &LaunchSettings::default(),
+            &join_server(address),
+        );
+        let server = args.iter().position(|arg| arg == "--server").unwrap();
+        (args[server + 1].clone(), args[server + 3].clone())
+    };
+    let expect = |host: &str, port: &str| (host.to_owned(), port.to_owned());
+
+    assert_eq!(server_args("localhost"), expect("localhost", "25565"));
+    assert_eq!(server_args("localhost:25566"), expect("localhost", "25566"));
+    assert_eq!(
+        server_args("localhost:99999"),
+        expect("localhost:99999", "25565")
+    );
+    assert_eq!(server_args("::1"), expect("::1", "25565"));
+    assert_eq!(server_args("2001:db8::1"), expect("2001:db8::1", "25565"));
+    assert_eq!(server_args("[2001:db8::1]"), expect("2001:db8::1", "25565"));
+    assert_eq!(
+        server_args("[2001:db8::1]:25566"),
+        expect("2001:db8::1", "25566")
+    );
+}
+
+#[test]
+fn quick_play_since_23w14a() {
+    let options = join_server("localhost:25566");
+    let args = |release_time| {
+        game_arguments(
+            &modern_client_of(release_time),
+            &LaunchSettings::default(),
+            &options,
+        )
+    };
+
+    let quick_play = args("2023-04-05T00:00:00+00:00");
+    assert!(quick_play.iter().any(|arg| arg == "--quickPlayMultiplayer"));
+    assert!(!quick_play.iter().any(|arg| arg == "--server"));
+
+    let legacy = args("2023-04-04T23:59:59+00:00");
+    assert!(!legacy.iter().any(|arg| arg == "--quickPlayMultiplayer"));
+    assert!(legacy.ends_with(&[
+        "--server".to_owned(),
+        "localhost".to_owned(),
+        "--port".to_owned(),
+        "25566".to_owned()
+    ]));
+
+    // Before Quick Play only servers can be joined
+    let world = LaunchOptions {
+        quick_play: Some(QuickPlay::Singleplayer("New World".to_owned())),
+        ..Default::default()
+    };
+    let client = modern_client_of("2023-04-04T23:59:59+00:00");
+    assert!(installation(&client)
+        .launch_arguments(&client, &LaunchSettings::default(), &world)
+        .is_err());
+}
+
+#[test]
+fn log4j_arguments() {
+    let jvm_arguments = |release_time| {
+        let client = client("1.x", release_time, json!("--username ${auth_player_name}"));
+        installation(&client)
+            .launch_arguments(
+                &client,
+                &LaunchSettings::default(),
+                &LaunchOptions::default(),
+            )
+            .unwrap()
+            .0
+    };
+    let has_config = |args: &[String]| {
+        args.iter().any(|arg| {
+            arg.starts_with("-Dlog4j.configurationFile=")
+                && arg.ends_with("client-1.12.xml")
+                && !arg.contains("${path}")
+        })
+    };
+    let no_lookups = |args: &[String]| {
+        args.iter()
+            .any(|arg| arg == "-Dlog4j2.formatMsgNoLookups=true")
+    };
+
+    // 1.12.2, its log4j is too old for the property, only the config protects it
+    let args = jvm_arguments("2017-09-18T08:39:46+00:00");
+    assert!(has_config(&args));
+    assert!(!no_lookups(&args));
+
+    // 1.17
+    let args = jvm_arguments("2021-06-08T11:00:40+00:00");
+    assert!(has_config(&args));
+    assert!(no_lookups(&args));
+
+    // 1.18.1
+    let args = jvm_arguments("2021-12-10T08:23:00+00:00");
+    assert!(has_config(&args));
+    assert!(!no_lookups(&args));
+}
+
+#[test]
+fn invalid_release_time() {
+    let client = client("1.x", "yesterday", json!("--username ${auth_player_name}"));
+    assert!(installation(&client)
+        .launch_arguments(
+            &client,
+            &LaunchSettings::default(),
+            &LaunchOptions::default()
+        )
+        .is_err());
+}
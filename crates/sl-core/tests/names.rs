@@ -0,0 +1,142 @@
+use serde_json::json;
+use sl_core::auth::{names::NameAvailability, AuthEndpoints, AuthServiceError, PlayerProfile};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn profile_json(name: &str) -> serde_json::Value {
+    json!({
+        "id": "069a79f444e94726a5befca90e38aaf5",
+        "name": name,
+        "skins": [],
+        "capes": []
+    })
+}
+
+async fn mock_profile(server: &MockServer) -> PlayerProfile {
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_json("Notch")))
+        .mount(server)
+        .await;
+
+    PlayerProfile::with_endpoints(
+        "minecraft-token".to_string(),
+        &AuthEndpoints::from_base(&server.uri()),
+    )
+    .await
+    .unwrap()
+}
+
+async fn change_name_error(response: ResponseTemplate) -> AuthServiceError {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("PUT"))
+        .and(path("/minecraft/profile/name/Jeb_"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+
+    profile.change_name("Jeb_").await.unwrap_err()
+}
+
+fn name_error(status: u16, details: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(json!({
+        "path": "/minecraft/profile/name/Jeb_",
+        "errorType": "FORBIDDEN",
+        "error": "FORBIDDEN",
+        "details": { "status": details }
+    }))
+}
+
+#[tokio::test]
+async fn name_availability() {
+    let server = MockServer::start().await;
+    let profile = mock_profile(&server).await;
+
+    for (name, status, expected) in [
+        ("Jeb_", "AVAILABLE", NameAvailability::Available),
+        ("Dinnerbone", "DUPLICATE", NameAvailability::Duplicate),
+        ("Blocked", "NOT_ALLOWED", NameAvailability::NotAllowed),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/minecraft/profile/name/{name}/available")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "status": status })))
+            .mount(&server)
+            .await;
+
+        assert_eq!(profile.name_availability(name).await.unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+async fn invalid_names_are_rejected_locally() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    let err = profile.name_availability("no spaces").await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidUsername(_)));
+
+    let err = profile.change_name("ab").await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidUsername(_)));
+}
+
+#[tokio::test]
+async fn name_change_status() {
+    let server = MockServer::start().await;
+    let profile = mock_profile(&server).await;
+
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile/namechange"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "changedAt": "2024-01-01T00:00:00Z",
+            "createdAt": "2010-01-01T00:00:00Z",
+            "nameChangeAllowed": false
+        })))
+        .mount(&server)
+        .await;
+
+    let status = profile.name_change_status().await.unwrap();
+    assert!(!status.name_change_allowed);
+    assert_eq!(status.changed_at.as_deref(), Some("2024-01-01T00:00:00Z"));
+}
+
+#[tokio::test]
+async fn change_name() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("PUT"))
+        .and(path("/minecraft/profile/name/Jeb_"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_json("Jeb_")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    profile.change_name("Jeb_").await.unwrap();
+    assert_eq!(profile.username, "Jeb_");
+    assert_eq!(profile.access_token, "minecraft-token");
+}
+
+#[tokio::test]
+async fn change_name_errors() {
+    let err = change_name_error(name_error(403, "DUPLICATE")).await;
+    assert!(matches!(err, AuthServiceError::NameTaken(name) if name == "Jeb_"));
+
+    let err = change_name_error(name_error(400, "NOT_ALLOWED")).await;
+    assert!(matches!(err, AuthServiceError::NameNotAllowed(name) if name == "Jeb_"));
+
+    let err = change_name_error(ResponseTemplate::new(403)).await;
+    assert!(matches!(err, AuthServiceError::NameChangeNotAllowed));
+
+    let err = change_name_error(ResponseTemplate::new(400)).await;
+    assert!(matches!(err, AuthServiceError::InvalidUsername(_)));
+
+    let err = change_name_error(ResponseTemplate::new(429)).await;
+    assert!(matches!(err, AuthServiceError::RateLimited));
+
+    let err = change_name_error(ResponseTemplate::new(401)).await;
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
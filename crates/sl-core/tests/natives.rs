@@ -0,0 +1,70 @@
+use std::{
+    fs,
+    io::{Cursor, Write},
+};
+
+use bytes::Bytes;
+use serde_json::json;
+use sl_core::{json::client::extract_natives, progress::ProgressReporter};
+use sl_meta::json::vanilla::Library;
+use tempfile::TempDir;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+fn natives_jar(library: &[u8]) -> Bytes {
+    let mut jar = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for (name, data) in [
+        ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0".as_slice()),
+        ("META-INF/LWJGL.SF", b"signature"),
+        ("liblwjgl.so", library),
+    ] {
+        jar.start_file(name, options).unwrap();
+        jar.write_all(data).unwrap();
+    }
+
+    Bytes::from(jar.finish().unwrap().into_inner())
+}
+
+fn lwjgl_platform() -> Library {
+    serde_json::from_value(json!({
+        "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+        "downloads": {
+            "classifiers": {
+                "natives-linux": {
+                    "path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar",
+                    "url": "https://libraries.minecraft.net/natives-linux.jar"
+                }
+            }
+        },
+        "extract": { "exclude": ["META-INF/"] },
+        "natives": { "linux": "natives-linux" }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn natives_are_extracted_once() {
+    let dir = TempDir::new().unwrap();
+    let natives_dir = dir.path().join(".natives");
+    let library = lwjgl_platform();
+    let native = &library.downloads.classifiers.as_ref().unwrap()["natives-linux"];
+    let progress = ProgressReporter::new(|_| {});
+
+    let extract =
+        |jar: Bytes| extract_natives(vec![(&library, native, jar)], &natives_dir, &progress);
+
+    extract(natives_jar(b"v1")).unwrap();
+    assert_eq!(fs::read(natives_dir.join("liblwjgl.so")).unwrap(), b"v1");
+    assert!(!natives_dir.join("META-INF").exists());
+
+    // The same jar isn't extracted again
+    fs::remove_file(natives_dir.join("liblwjgl.so")).unwrap();
+    extract(natives_jar(b"v1")).unwrap();
+    assert!(!natives_dir.join("liblwjgl.so").exists());
+
+    // A changed one is
+    extract(natives_jar(b"v2")).unwrap();
+    assert_eq!(fs::read(natives_dir.join("liblwjgl.so")).unwrap(), b"v2");
+    assert!(!natives_dir.join("META-INF").exists());
+}
@@ -0,0 +1,45 @@
+use serde_json::json;
+use sl_core::auth::{yggdrasil::YggdrasilClient, AuthEndpoints, AuthServiceError, PlayerProfile};
+use sl_utils::utils::http;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+// In its own test binary, as offline mode applies to the whole process
+#[tokio::test]
+async fn profile_requests_are_refused_offline() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "069a79f444e94726a5befca90e38aaf5",
+            "name": "Notch",
+            "skins": [],
+            "capes": []
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut profile = PlayerProfile::with_endpoints(
+        "minecraft-token".to_string(),
+        &AuthEndpoints::from_base(&server.uri()),
+    )
+    .await
+    .unwrap();
+
+    http::set_offline(true);
+
+    let offline = |err: AuthServiceError| assert!(matches!(err, AuthServiceError::Offline));
+    offline(profile.reset_skin().await.unwrap_err());
+    offline(profile.set_cape(None).await.unwrap_err());
+    offline(profile.name_change_status().await.unwrap_err());
+    offline(profile.name_availability("Jeb_").await.unwrap_err());
+    offline(profile.change_name("Jeb_").await.unwrap_err());
+    assert!(matches!(
+        YggdrasilClient::discover(&server.uri()).await,
+        Err(AuthServiceError::Offline)
+    ));
+}
@@ -0,0 +1,117 @@
+use std::fs;
+
+use sl_core::auth::secrets::{FileSecretBackend, KeySource, SecretBackend, SecretError};
+use tempfile::TempDir;
+
+fn passphrase(value: &str) -> KeySource {
+    KeySource::Passphrase(value.to_string())
+}
+
+#[test]
+fn passphrase_roundtrip() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("secrets.json");
+
+    let backend = FileSecretBackend::open(path.clone(), passphrase("hunter2")).unwrap();
+    backend.set("microsoft:abc", "refresh-token").unwrap();
+    drop(backend);
+
+    let backend = FileSecretBackend::open(path, passphrase("hunter2")).unwrap();
+    assert_eq!(
+        backend.get("microsoft:abc").unwrap().as_deref(),
+        Some("refresh-token")
+    );
+    assert_eq!(backend.get("missing").unwrap(), None);
+}
+
+#[test]
+fn secrets_are_not_stored_in_plaintext() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("secrets.json");
+
+    let backend = FileSecretBackend::open(path.clone(), passphrase("hunter2")).unwrap();
+    backend.set("token", "super-secret-token").unwrap();
+
+    let contents = fs::read_to_string(path).unwrap();
+    assert!(!contents.contains("super-secret-token"));
+}
+
+#[test]
+fn wrong_passphrase_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("secrets.json");
+
+    FileSecretBackend::open(path.clone(), passphrase("hunter2")).unwrap();
+
+    let result = FileSecretBackend::open(path, passphrase("hunter3"));
+    assert!(matches!(result, Err(SecretError::WrongKey)));
+}
+
+#[test]
+fn key_file_is_generated_and_reused() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("secrets.json");
+    let key_file = dir.path().join("secrets.key");
+
+    let backend =
+        FileSecretBackend::open(path.clone(), KeySource::KeyFile(key_file.clone())).unwrap();
+    backend.set("token", "value").unwrap();
+    assert_eq!(fs::read(&key_file).unwrap().len(), 32);
+    drop(backend);
+
+    let backend = FileSecretBackend::open(path.clone(), KeySource::KeyFile(key_file)).unwrap();
+    assert_eq!(backend.get("token").unwrap().as_deref(), Some("value"));
+
+    let other_key = dir.path().join("other.key");
+    let result = FileSecretBackend::open(path, KeySource::KeyFile(other_key));
+    assert!(matches!(result, Err(SecretError::WrongKey)));
+}
+
+#[test]
+fn invalid_key_file_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let key_file = dir.path().join("secrets.key");
+    fs::write(&key_file, b"too short").unwrap();
+
+    let result = FileSecretBackend::open(
+        dir.path().join("secrets.json"),
+        KeySource::KeyFile(key_file),
+    );
+    assert!(matches!(result, Err(SecretError::InvalidKeyFile(_))));
+}
+
+#[test]
+fn delete_and_keys() {
+    let dir = TempDir::new().unwrap();
+    let backend =
+        FileSecretBackend::open(dir.path().join("secrets.json"), passphrase("hunter2")).unwrap();
+
+    backend.set("a", "1").unwrap();
+    backend.set("b", "2").unwrap();
+    backend.delete("a").unwrap();
+
+    assert_eq!(backend.keys().unwrap(), vec!["b".to_string()]);
+    assert_eq!(backend.get("a").unwrap(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("secrets.json");
+    let key_file = dir.path().join("secrets.key");
+
+    let backend =
+        FileSecretBackend::open(path.clone(), KeySource::KeyFile(key_file.clone())).unwrap();
+    backend.set("token", "value").unwrap();
+
+    for file in [path, key_file] {
+        assert_eq!(
+            fs::metadata(file).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+    assert!(!dir.path().join("secrets.json.tmp").exists());
+}
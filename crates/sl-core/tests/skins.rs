@@ -0,0 +1,243 @@
+use serde_json::{json, Value};
+use sl_core::auth::{
+    skins::{validate_skin, SkinVariant, TextureState},
+    AuthEndpoints, AuthServiceError, PlayerProfile,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{body_json, header, method, path},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+/// The start of a PNG, enough for the skin validation to read its size.
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+    png.extend_from_slice(&width.to_be_bytes());
+    png.extend_from_slice(&height.to_be_bytes());
+    png.extend_from_slice(&[8, 6, 0, 0, 0]);
+    png
+}
+
+/// Like `body_string_contains`, for bodies that aren't UTF-8 such as the uploaded PNG.
+fn body_contains(needle: &'static [u8]) -> impl Fn(&Request) -> bool {
+    move |request| {
+        request
+            .body
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+}
+
+fn profile_json(skin_variant: &str, cape_state: &str) -> Value {
+    json!({
+        "id": "069a79f444e94726a5befca90e38aaf5",
+        "name": "Notch",
+        "skins": [{
+            "id": "skin-id",
+            "state": "ACTIVE",
+            "url": "http://textures.minecraft.net/texture/skin",
+            "variant": skin_variant,
+            "textureKey": "skin"
+        }],
+        "capes": [{
+            "id": "cape-id",
+            "state": cape_state,
+            "url": "http://textures.minecraft.net/texture/cape",
+            "alias": "Migrator"
+        }]
+    })
+}
+
+async fn mock_profile(server: &MockServer) -> PlayerProfile {
+    Mock::given(method("GET"))
+        .and(path("/minecraft/profile"))
+        .and(header("authorization", "Bearer minecraft-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_json("CLASSIC", "INACTIVE")))
+        .mount(server)
+        .await;
+
+    PlayerProfile::with_endpoints(
+        "minecraft-token".to_string(),
+        &AuthEndpoints::from_base(&server.uri()),
+    )
+    .await
+    .unwrap()
+}
+
+#[test]
+fn skin_sizes() {
+    assert!(validate_skin(&png(64, 64)).is_ok());
+    assert!(validate_skin(&png(64, 32)).is_ok());
+
+    for (width, height) in [(32, 32), (128, 128), (64, 48)] {
+        let err = validate_skin(&png(width, height)).unwrap_err();
+        assert!(matches!(err, AuthServiceError::InvalidSkin(_)));
+    }
+
+    let err = validate_skin(b"GIF89a not a png at all").unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidSkin(_)));
+}
+
+#[tokio::test]
+async fn profile_has_skins_and_capes() {
+    let server = MockServer::start().await;
+    let profile = mock_profile(&server).await;
+
+    let skin = profile.active_skin().unwrap();
+    assert_eq!(skin.id, "skin-id");
+    assert_eq!(skin.variant, SkinVariant::Classic);
+
+    assert_eq!(profile.capes.len(), 1);
+    assert_eq!(profile.capes[0].state, TextureState::Inactive);
+    assert!(profile.active_cape().is_none());
+}
+
+#[tokio::test]
+async fn upload_skin() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/minecraft/profile/skins"))
+        .and(header("authorization", "Bearer minecraft-token"))
+        .and(body_contains(b"name=\"variant\"\r\n\r\nslim"))
+        .and(body_contains(
+            b"filename=\"skin.png\"\r\nContent-Type: image/png",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_json("SLIM", "INACTIVE")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let skin = dir.path().join("skin.png");
+    std::fs::write(&skin, png(64, 64)).unwrap();
+
+    profile.upload_skin(&skin, SkinVariant::Slim).await.unwrap();
+    assert_eq!(profile.active_skin().unwrap().variant, SkinVariant::Slim);
+    assert_eq!(profile.access_token, "minecraft-token");
+}
+
+#[tokio::test]
+async fn invalid_skin_is_not_uploaded() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/minecraft/profile/skins"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let skin = dir.path().join("skin.png");
+    std::fs::write(&skin, png(128, 128)).unwrap();
+
+    let err = profile
+        .upload_skin(&skin, SkinVariant::Classic)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidSkin(_)));
+}
+
+#[tokio::test]
+async fn rejected_skin_and_rate_limits() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/minecraft/profile/skins"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "path": "/minecraft/profile/skins",
+            "errorType": "BAD REQUEST",
+            "error": "IllegalArgumentException",
+            "errorMessage": "Could not validate image data."
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/minecraft/profile/skins/active"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let skin = dir.path().join("skin.png");
+    std::fs::write(&skin, png(64, 64)).unwrap();
+
+    let err = profile
+        .upload_skin(&skin, SkinVariant::Classic)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, AuthServiceError::InvalidSkin(ref message) if message == "Could not validate image data.")
+    );
+
+    let err = profile.reset_skin().await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::RateLimited));
+}
+
+#[tokio::test]
+async fn reset_skin() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/minecraft/profile/skins/active"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "069a79f444e94726a5befca90e38aaf5",
+            "name": "Notch",
+            "skins": [],
+            "capes": []
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    profile.reset_skin().await.unwrap();
+    assert!(profile.active_skin().is_none());
+}
+
+#[tokio::test]
+async fn set_cape_by_name() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("PUT"))
+        .and(path("/minecraft/profile/capes/active"))
+        .and(body_json(json!({ "capeId": "cape-id" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_json("CLASSIC", "ACTIVE")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    profile.set_cape(Some("migrator")).await.unwrap();
+    assert_eq!(profile.active_cape().unwrap().id, "cape-id");
+}
+
+#[tokio::test]
+async fn hide_cape() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/minecraft/profile/capes/active"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_json("CLASSIC", "INACTIVE")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    profile.set_cape(None).await.unwrap();
+    assert!(profile.active_cape().is_none());
+}
+
+#[tokio::test]
+async fn unknown_cape() {
+    let server = MockServer::start().await;
+    let mut profile = mock_profile(&server).await;
+
+    let err = profile.set_cape(Some("Vanilla")).await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::CapeNotFound(name) if name == "Vanilla"));
+}
@@ -0,0 +1,53 @@
+use sl_core::json::client::{check_file, FileStatus};
+use sl_meta::json::vanilla::Download;
+use tempfile::TempDir;
+
+const DATA: &[u8] = b"hello world";
+const DATA_SHA1: &str = "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed";
+
+fn download(sha1: &str, size: i32) -> Download {
+    Download {
+        path: None,
+        url: "https://example.com/file".to_string(),
+        sha1: Some(sha1.to_string()),
+        size: Some(size),
+    }
+}
+
+#[test]
+fn check_file_status() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("file");
+    let expected = download(DATA_SHA1, DATA.len() as i32);
+
+    assert_eq!(check_file(&expected, &path), FileStatus::Missing);
+
+    std::fs::write(&path, DATA).unwrap();
+    assert_eq!(check_file(&expected, &path), FileStatus::Valid);
+    assert_eq!(
+        check_file(&download(&DATA_SHA1.to_uppercase(), 11), &path),
+        FileStatus::Valid
+    );
+
+    // Same size, different content
+    std::fs::write(&path, b"hello_world").unwrap();
+    assert_eq!(check_file(&expected, &path), FileStatus::Corrupted);
+
+    std::fs::write(&path, b"hello").unwrap();
+    assert_eq!(check_file(&expected, &path), FileStatus::Corrupted);
+}
+
+#[test]
+fn check_file_without_hash() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, DATA).unwrap();
+
+    let unknown = Download {
+        path: None,
+        url: "https://example.com/file".to_string(),
+        sha1: None,
+        size: None,
+    };
+    assert_eq!(check_file(&unknown, &path), FileStatus::Valid);
+}
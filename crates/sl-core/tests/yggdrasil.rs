@@ -0,0 +1,213 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use sl_core::auth::{
+    yggdrasil::{YggdrasilAccount, YggdrasilClient},
+    AuthServiceError,
+};
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const API: &str = "/api/yggdrasil";
+
+fn session(access_token: &str) -> Value {
+    json!({
+        "accessToken": access_token,
+        "clientToken": "client-token",
+        "selectedProfile": { "id": "0123456789abcdef0123456789abcdef", "name": "Steve" },
+        "availableProfiles": [
+            { "id": "0123456789abcdef0123456789abcdef", "name": "Steve" }
+        ]
+    })
+}
+
+fn forbidden(message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(403).set_body_json(json!({
+        "error": "ForbiddenOperationException",
+        "errorMessage": message
+    }))
+}
+
+/// A stand-in skin server whose homepage points to its API through
+/// the `X-Authlib-Injector-API-Location` header.
+async fn mock_server() -> MockServer {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-Authlib-Injector-API-Location", "/api/yggdrasil/"),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(API))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "meta": { "serverName": "Test server" },
+            "skinDomains": ["localhost"]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/authenticate")))
+        .and(body_string_contains("\"password\":\"hunter2\""))
+        .respond_with(ResponseTemplate::new(200).set_body_json(session("access-token")))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/authenticate")))
+        .respond_with(forbidden(
+            "Invalid credentials. Invalid username or password.",
+        ))
+        .mount(&server)
+        .await;
+
+    server
+}
+
+async fn login(server: &MockServer) -> YggdrasilAccount {
+    YggdrasilAccount::login(&server.uri(), "steve@example.com", "hunter2")
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn login_follows_api_location() {
+    let server = mock_server().await;
+    let account = login(&server).await;
+
+    assert_eq!(account.server, format!("{}{API}", server.uri()));
+    assert_eq!(account.username, "Steve");
+    assert_eq!(account.uuid, "0123456789abcdef0123456789abcdef");
+    assert_eq!(account.access_token, "access-token");
+    assert_eq!(account.client_token, "client-token");
+}
+
+#[tokio::test]
+async fn login_with_wrong_password() {
+    let server = mock_server().await;
+
+    let err = YggdrasilAccount::login(&server.uri(), "steve@example.com", "hunter3")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidCredentials));
+}
+
+#[tokio::test]
+async fn valid_token_is_not_refreshed() {
+    let server = mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/validate")))
+        .and(body_string_contains("access-token"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/refresh")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(session("new-token")))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let mut account = login(&server).await;
+    account.ensure_valid().await.unwrap();
+    assert_eq!(account.access_token, "access-token");
+}
+
+#[tokio::test]
+async fn invalid_token_is_refreshed() {
+    let server = mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/validate")))
+        .respond_with(forbidden("Invalid token."))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/refresh")))
+        .and(body_string_contains("\"accessToken\":\"access-token\""))
+        .and(body_string_contains("\"clientToken\":\"client-token\""))
+        .respond_with(ResponseTemplate::new(200).set_body_json(session("new-token")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut account = login(&server).await;
+    account.ensure_valid().await.unwrap();
+    assert_eq!(account.access_token, "new-token");
+}
+
+#[tokio::test]
+async fn rejected_refresh() {
+    let server = mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/validate")))
+        .respond_with(forbidden("Invalid token."))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/refresh")))
+        .respond_with(forbidden("Invalid token."))
+        .mount(&server)
+        .await;
+
+    let mut account = login(&server).await;
+    let err = account.ensure_valid().await.unwrap_err();
+    assert!(matches!(err, AuthServiceError::InvalidAccessToken));
+}
+
+#[tokio::test]
+async fn logout_invalidates_token() {
+    let server = mock_server().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{API}/authserver/invalidate")))
+        .and(body_string_contains("access-token"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    login(&server).await.logout().await.unwrap();
+}
+
+#[tokio::test]
+async fn authlib_injector_arguments() {
+    let server = mock_server().await;
+    let mut account = login(&server).await;
+
+    let metadata = YggdrasilClient::new(&account.server)
+        .metadata()
+        .await
+        .unwrap();
+    assert!(metadata.contains("Test server"));
+    account.metadata = Some(metadata.clone());
+
+    let args = account.jvm_arguments(Path::new("/libs/authlib-injector.jar"));
+    assert_eq!(
+        args,
+        vec![
+            format!(
+                "-javaagent:/libs/authlib-injector.jar={}{API}",
+                server.uri()
+            ),
+            format!(
+                "-Dauthlibinjector.yggdrasil.prefetched={}",
+                STANDARD.encode(metadata)
+            ),
+        ]
+    );
+}
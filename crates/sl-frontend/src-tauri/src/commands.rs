@@ -1,27 +1,341 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use sl_core::auth::accounts::{Account, Accounts, MicrosoftAccount, OfflineAccount};
+use sl_core::auth::names::{NameAvailability, NameChangeStatus};
+use sl_core::auth::skins::{Cape, Skin, SkinVariant};
+use sl_core::auth::yggdrasil::YggdrasilAccount;
+use sl_core::auth::{
+    CancellationToken, LoginEvent, LoginMethod, PlayerProfile, MICROSOFT_CLIENT_ID,
+};
 use sl_core::config::config::Config;
 use sl_core::installations::{Installation, Installations};
+use sl_core::json::client::VerifyReport;
+use sl_core::launch::{LaunchOptions, LaunchSettings, QuickPlay};
+use sl_core::progress::{InstallEvent, ProgressReporter};
+use sl_utils::utils::http;
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_opener::OpenerExt;
+
+/// Lets the frontend cancel the Microsoft login that is waiting for the user.
+#[derive(Default)]
+pub struct LoginState(Mutex<Option<CancellationToken>>);
 
-async fn get_global_config() -> Result<Config, std::io::Error> {
-    Ok(Config::read_global()?)
+impl LoginState {
+    fn start(&self) -> CancellationToken {
+        let cancel = CancellationToken::new();
+        *self.0.lock().unwrap() = Some(cancel.clone());
+        cancel
+    }
+}
+
+fn emit_login_event(app: &AppHandle, event: LoginEvent) {
+    if let LoginEvent::OpenBrowser { url } = &event {
+        let _ = app.opener().open_url(url, None::<&str>);
+    }
+    let _ = app.emit("microsoft-login", event);
+}
+
+#[derive(Serialize)]
+pub struct AccountInfo {
+    id: String,
+    username: String,
+    premium: bool,
+    active: bool,
+}
+
+fn load_accounts() -> Result<Accounts, String> {
+    Accounts::load().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_username() -> Result<String, String> {
-    let config = get_global_config().await.map_err(|e| e.to_string())?;
-    let username = config
-        .get("auth_player_name")
-        .ok_or("Missing 'auth_player_name' in config")?
+    let accounts = load_accounts()?;
+    let username = accounts
+        .active()
+        .ok_or("No account is selected")?
+        .username()
         .to_string();
     Ok(username)
 }
 
 #[tauri::command]
 pub async fn edit_username(username: &str) -> Result<(), String> {
-    let mut config = get_global_config().await.map_err(|e| e.to_string())?;
+    let mut accounts = load_accounts()?;
+
+    if let Some(Account::Offline(old)) = accounts.active().cloned() {
+        accounts.remove(&old.username).map_err(|e| e.to_string())?;
+    }
+
+    let account = OfflineAccount::new(username).map_err(|e| e.to_string())?;
+    accounts.add(Account::Offline(account));
+    accounts.switch(username).map_err(|e| e.to_string())?;
+    accounts.overwrite().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_accounts() -> Result<Vec<AccountInfo>, String> {
+    let accounts = load_accounts()?;
+
+    Ok(accounts
+        .accounts
+        .iter()
+        .map(|account| AccountInfo {
+            id: account.id().to_string(),
+            username: account.username().to_string(),
+            premium: !matches!(account, Account::Offline(_)),
+            active: accounts.active.as_deref() == Some(account.id()),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn add_offline_account(username: &str) -> Result<(), String> {
+    let mut accounts = load_accounts()?;
+    let account = OfflineAccount::new(username).map_err(|e| e.to_string())?;
+    accounts.add(Account::Offline(account));
+    accounts.overwrite().map_err(|e| e.to_string())
+}
+
+/// Emits `microsoft-login` events with the device code and the time left to enter it,
+/// or the login page opened in the browser.
+#[tauri::command]
+pub async fn add_microsoft_account(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    method: LoginMethod,
+) -> Result<(), String> {
+    let cancel = login.start();
+    let account = MicrosoftAccount::login(MICROSOFT_CLIENT_ID, method, &cancel, |event| {
+        emit_login_event(&app, event)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut accounts = load_accounts()?;
+    accounts.add(Account::Microsoft(account));
+    accounts.overwrite().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_yggdrasil_account(
+    server: &str,
+    username: &str,
+    password: &str,
+) -> Result<(), String> {
+    let account = YggdrasilAccount::login(server, username, password)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut accounts = load_accounts()?;
+    accounts.add(Account::Yggdrasil(account));
+    accounts.overwrite().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cancel_microsoft_login(login: State<'_, LoginState>) {
+    if let Some(cancel) = login.0.lock().unwrap().take() {
+        cancel.cancel();
+    }
+}
+
+#[tauri::command]
+pub async fn remove_account(name: &str) -> Result<(), String> {
+    let mut accounts = load_accounts()?;
+    if let Account::Yggdrasil(account) = accounts.remove(name).map_err(|e| e.to_string())? {
+        let _ = account.logout().await;
+    }
+    accounts.overwrite().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn switch_account(name: &str) -> Result<(), String> {
+    let mut accounts = load_accounts()?;
+    accounts.switch(name).map_err(|e| e.to_string())?;
+    accounts.overwrite().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_installation_account(name: &str, account: Option<String>) -> Result<(), String> {
+    let mut instance = Installations::find(name).map_err(|e| e.to_string())?;
+    instance
+        .set_account(account.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_launch_settings(name: &str) -> Result<LaunchSettings, String> {
+    let instance = Installations::find(name).map_err(|e| e.to_string())?;
+    Ok(instance.launch_settings())
+}
+
+#[tauri::command]
+pub async fn set_launch_settings(name: &str, settings: LaunchSettings) -> Result<(), String> {
+    let mut instance = Installations::find(name).map_err(|e| e.to_string())?;
+    instance
+        .set_launch_settings(&settings)
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Serialize)]
+pub struct SkinsInfo {
+    skin: Option<Skin>,
+    capes: Vec<Cape>,
+}
+
+impl From<PlayerProfile> for SkinsInfo {
+    fn from(profile: PlayerProfile) -> Self {
+        Self {
+            skin: profile.active_skin().cloned(),
+            capes: profile.capes,
+        }
+    }
+}
+
+/// The profile of `account` (or the active account), logging in again if its tokens expired.
+async fn load_profile(
+    app: &AppHandle,
+    login: &LoginState,
+    account: Option<&str>,
+    method: LoginMethod,
+) -> Result<PlayerProfile, String> {
+    let cancel = login.start();
+    let account = Accounts::resolve(MICROSOFT_CLIENT_ID, account, method, &cancel, |event| {
+        emit_login_event(app, event)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    account.profile().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_skins(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+) -> Result<SkinsInfo, String> {
+    let profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    Ok(profile.into())
+}
+
+#[tauri::command]
+pub async fn upload_skin(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+    path: &str,
+    variant: SkinVariant,
+) -> Result<SkinsInfo, String> {
+    let mut profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    profile
+        .upload_skin(Path::new(path), variant)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(profile.into())
+}
+
+#[tauri::command]
+pub async fn reset_skin(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+) -> Result<SkinsInfo, String> {
+    let mut profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    profile.reset_skin().await.map_err(|e| e.to_string())?;
+    Ok(profile.into())
+}
+
+/// Shows the cape with the id or name `cape`, or hides the cape if it's `None`.
+#[tauri::command]
+pub async fn set_cape(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+    cape: Option<String>,
+) -> Result<SkinsInfo, String> {
+    let mut profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    profile
+        .set_cape(cape.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(profile.into())
+}
+
+#[tauri::command]
+pub async fn check_name_availability(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+    name: &str,
+) -> Result<NameAvailability, String> {
+    let profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    profile
+        .name_availability(name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_name_change_status(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+) -> Result<NameChangeStatus, String> {
+    let profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    profile
+        .name_change_status()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Renames the player and the stored account, returns the new name.
+#[tauri::command]
+pub async fn change_name(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    account: Option<String>,
+    method: LoginMethod,
+    name: &str,
+) -> Result<String, String> {
+    let mut profile = load_profile(&app, &login, account.as_deref(), method).await?;
+    profile.change_name(name).await.map_err(|e| e.to_string())?;
+
+    let mut accounts = load_accounts()?;
+    if let Some(Account::Microsoft(microsoft)) = accounts.find(&profile.uuid).cloned() {
+        accounts.add(Account::Microsoft(MicrosoftAccount {
+            username: profile.username.clone(),
+            ..microsoft
+        }));
+        accounts.overwrite().map_err(|e| e.to_string())?;
+    }
+
+    Ok(profile.username)
+}
+
+#[tauri::command]
+pub fn get_offline_mode() -> bool {
+    http::is_offline()
+}
+
+/// Remembered in the global config, so the launcher starts offline next time too.
+#[tauri::command]
+pub fn set_offline_mode(offline: bool) -> Result<(), String> {
+    let mut config = Config::read_global().map_err(|e| e.to_string())?;
     config
-        .update_config_field("auth_player_name", username)
-        .unwrap();
+        .update_config_field("offline", &offline.to_string())
+        .map_err(|e| e.to_string())?;
 
+    http::set_offline(offline);
     Ok(())
 }
 
@@ -32,11 +346,33 @@ pub async fn get_installations() -> Result<Installations, String> {
     Ok(installations)
 }
 
+/// An install event, with the installation it's about.
+#[derive(Clone, Serialize)]
+struct InstallProgress {
+    name: String,
+    #[serde(flatten)]
+    event: InstallEvent,
+}
+
 #[tauri::command]
-pub async fn create_installation(name: &str, version: &str) -> Result<(), String> {
-    let mut instance = Installation::new(&name, &version).unwrap();
-    Installations::add(&instance).unwrap();
-    instance.install().await.unwrap();
+pub async fn create_installation(app: AppHandle, name: &str, version: &str) -> Result<(), String> {
+    let mut instance = Installation::new(&name, &version).map_err(|e| e.to_string())?;
+    Installations::add(&instance).map_err(|e| e.to_string())?;
+
+    let installation = name.to_owned();
+    let progress = ProgressReporter::new(move |event| {
+        let _ = app.emit(
+            "install-progress",
+            InstallProgress {
+                name: installation.clone(),
+                event,
+            },
+        );
+    });
+    instance
+        .install(&progress)
+        .await
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
@@ -47,6 +383,18 @@ pub async fn remove_installation(name: &str) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn verify_installation(name: &str) -> Result<VerifyReport, String> {
+    let mut instance = Installations::find(name).map_err(|e| e.to_string())?;
+    instance.verify().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn repair_installation(name: &str) -> Result<VerifyReport, String> {
+    let mut instance = Installations::find(name).map_err(|e| e.to_string())?;
+    instance.repair().await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn load_all_installations() -> Result<(), String> {
     for instance in Installations::load_all_installations().unwrap().0 {
@@ -56,9 +404,39 @@ pub async fn load_all_installations() -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn launch(name: &str) -> Result<(), String> {
+pub async fn launch(
+    app: AppHandle,
+    login: State<'_, LoginState>,
+    name: &str,
+    method: LoginMethod,
+    quick_play: Option<QuickPlay>,
+) -> Result<(), String> {
     let instance = Installations::find(name).map_err(|e| e.to_string())?;
-    instance.execute(None).map_err(|e| e.to_string())?;
+    let cancel = login.start();
+    let account = Accounts::resolve(
+        MICROSOFT_CLIENT_ID,
+        instance.account().as_deref(),
+        method,
+        &cancel,
+        |event| emit_login_event(&app, event),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Lets a maximized instance open as large as the screen
+    let screen_size = app.primary_monitor().ok().flatten().map(|monitor| {
+        let size = monitor.size().to_logical::<u32>(monitor.scale_factor());
+        (size.width, size.height)
+    });
+    let options = LaunchOptions {
+        screen_size,
+        quick_play,
+    };
+
+    instance
+        .execute(&account, &options)
+        .await
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
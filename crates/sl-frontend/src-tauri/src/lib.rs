@@ -1,4 +1,13 @@
-use crate::commands::{launch, get_username, edit_username, get_installations, create_installation, remove_installation, load_all_installations};
+use crate::commands::{
+    add_microsoft_account, add_offline_account, add_yggdrasil_account, cancel_microsoft_login,
+    change_name, check_name_availability, create_installation, edit_username, get_accounts,
+    get_installations, get_launch_settings, get_name_change_status, get_offline_mode, get_skins,
+    get_username, launch, load_all_installations, remove_account, remove_installation,
+    repair_installation, reset_skin, set_cape, set_installation_account, set_launch_settings,
+    set_offline_mode, switch_account, upload_skin, verify_installation, LoginState,
+};
+
+use sl_core::config::init_launcher_dir;
 
 mod commands;
 
@@ -7,14 +16,37 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        .manage(LoginState::default())
+        .setup(|_| Ok(tauri::async_runtime::block_on(init_launcher_dir())?))
         .invoke_handler(tauri::generate_handler![
             launch,
             get_username,
             edit_username,
+            get_offline_mode,
+            set_offline_mode,
             get_installations,
             create_installation,
             remove_installation,
-            load_all_installations
+            load_all_installations,
+            verify_installation,
+            repair_installation,
+            get_accounts,
+            add_offline_account,
+            add_microsoft_account,
+            add_yggdrasil_account,
+            cancel_microsoft_login,
+            remove_account,
+            switch_account,
+            set_installation_account,
+            get_launch_settings,
+            set_launch_settings,
+            get_skins,
+            upload_skin,
+            reset_skin,
+            set_cape,
+            check_name_availability,
+            get_name_change_status,
+            change_name
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,7 +1,20 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { message } from "@tauri-apps/plugin-dialog";
-import { Installation } from "./types";
+import {
+	Account,
+	Installation,
+	InstallProgress,
+	LaunchSettings,
+	LoginMethod,
+	NameAvailability,
+	QuickPlay,
+	NameChangeStatus,
+	Skins,
+	SkinVariant,
+	VerifyReport,
+} from "./types";
 
 // TODO: Add unmaximizing!!!
 export const handleWinndowMaximize = async () => {
@@ -33,9 +46,14 @@ export const getPlayerUsername = async (
 	}
 };
 
-export const launchInstance = async (name: string) => {
+// Goes straight into a world, server or realm with `quickPlay`
+export const launchInstance = async (
+	name: string,
+	method: LoginMethod = "device_code",
+	quickPlay?: QuickPlay
+) => {
 	try {
-		await invoke("launch", { name: name });
+		await invoke("launch", { name: name, method: method, quickPlay: quickPlay });
 	} catch (error) {
 		await message(`Launching error: ${error}`, {
 			title: "SynthLauncher",
@@ -58,10 +76,24 @@ export const getInstances = async (
 	}
 };
 
+export const getOfflineMode = async (): Promise<boolean> => {
+	return await invoke("get_offline_mode");
+};
+
+export const setOfflineMode = async (offline: boolean) => {
+	await invoke("set_offline_mode", { offline: offline });
+};
+
 export const createInstance = async (name: string, version: string) => {
 	await invoke("create_installation", { name: name, version: version });
 };
 
+export const onInstallProgress = async (
+	callback: (progress: InstallProgress) => void
+): Promise<UnlistenFn> => {
+	return await listen<InstallProgress>("install-progress", (event) => callback(event.payload));
+};
+
 export const removeInstance = async (name: string) => {
 	await invoke("remove_installation", { name: name });
 };
@@ -69,3 +101,136 @@ export const removeInstance = async (name: string) => {
 export const loadInstances = async () => {
 	await invoke("load_all_installations");
 };
+
+export const getAccounts = async (setAccounts: (accounts: Account[]) => void) => {
+	try {
+		const accounts: Account[] = await invoke("get_accounts");
+		setAccounts(accounts);
+	} catch (error) {
+		await message(`Failed to get accounts: ${error}`, {
+			title: "SynthLauncher",
+			kind: "error",
+		});
+	}
+};
+
+export const addOfflineAccount = async (username: string) => {
+	await invoke("add_offline_account", { username: username });
+};
+
+// Listen to "microsoft-login" to show the device code and its countdown to the user,
+// the browser login page is opened by the backend
+export const addMicrosoftAccount = async (method: LoginMethod = "device_code") => {
+	await invoke("add_microsoft_account", { method: method });
+};
+
+export const addYggdrasilAccount = async (
+	server: string,
+	username: string,
+	password: string
+) => {
+	await invoke("add_yggdrasil_account", {
+		server: server,
+		username: username,
+		password: password,
+	});
+};
+
+export const cancelMicrosoftLogin = async () => {
+	await invoke("cancel_microsoft_login");
+};
+
+export const removeAccount = async (name: string) => {
+	await invoke("remove_account", { name: name });
+};
+
+export const switchAccount = async (name: string) => {
+	await invoke("switch_account", { name: name });
+};
+
+export const setInstanceAccount = async (name: string, account?: string) => {
+	await invoke("set_installation_account", { name: name, account: account });
+};
+
+// These log in again if the account's tokens expired, see addMicrosoftAccount
+export const getSkins = async (
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<Skins> => {
+	return await invoke("get_skins", { account: account, method: method });
+};
+
+export const uploadSkin = async (
+	path: string,
+	variant: SkinVariant,
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<Skins> => {
+	return await invoke("upload_skin", {
+		account: account,
+		method: method,
+		path: path,
+		variant: variant,
+	});
+};
+
+export const resetSkin = async (
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<Skins> => {
+	return await invoke("reset_skin", { account: account, method: method });
+};
+
+// Omit the cape to hide it
+export const setCape = async (
+	cape?: string,
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<Skins> => {
+	return await invoke("set_cape", { account: account, method: method, cape: cape });
+};
+
+export const checkNameAvailability = async (
+	name: string,
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<NameAvailability> => {
+	return await invoke("check_name_availability", {
+		account: account,
+		method: method,
+		name: name,
+	});
+};
+
+export const getNameChangeStatus = async (
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<NameChangeStatus> => {
+	return await invoke("get_name_change_status", { account: account, method: method });
+};
+
+// Resolves to the new name
+export const changeName = async (
+	name: string,
+	account?: string,
+	method: LoginMethod = "device_code"
+): Promise<string> => {
+	return await invoke("change_name", { account: account, method: method, name: name });
+};
+
+export const verifyInstallation = async (name: string): Promise<VerifyReport> => {
+	return await invoke("verify_installation", { name: name });
+};
+
+// Resolves to the files that were downloaded again
+export const repairInstallation = async (name: string): Promise<VerifyReport> => {
+	return await invoke("repair_installation", { name: name });
+};
+
+export const getLaunchSettings = async (name: string): Promise<LaunchSettings> => {
+	return await invoke("get_launch_settings", { name: name });
+};
+
+export const setLaunchSettings = async (name: string, settings: LaunchSettings) => {
+	await invoke("set_launch_settings", { name: name, settings: settings });
+};
@@ -24,3 +24,85 @@ export type InstanceCardProps = {
 	image: string;
 	favorite?: boolean;
 };
+
+export interface Account {
+	id: string;
+	username: string;
+	premium: boolean;
+	active: boolean;
+}
+
+export type LoginMethod = "device_code" | "browser";
+
+export type SkinVariant = "CLASSIC" | "SLIM";
+
+export interface Skin {
+	id: string;
+	state: "ACTIVE" | "INACTIVE";
+	url: string;
+	variant: SkinVariant;
+	alias?: string;
+}
+
+export interface Cape {
+	id: string;
+	state: "ACTIVE" | "INACTIVE";
+	url: string;
+	alias: string;
+}
+
+export interface Skins {
+	skin?: Skin;
+	capes: Cape[];
+}
+
+export type NameAvailability = "AVAILABLE" | "DUPLICATE" | "NOT_ALLOWED";
+
+export interface NameChangeStatus {
+	changedAt?: string;
+	createdAt?: string;
+	nameChangeAllowed: boolean;
+}
+
+export interface LaunchSettings {
+	width?: number;
+	height?: number;
+	fullscreen: boolean;
+	demo: boolean;
+	maximized: boolean;
+}
+
+export type QuickPlay =
+	| { kind: "singleplayer"; target: string }
+	| { kind: "multiplayer"; target: string }
+	| { kind: "realms"; target: string };
+
+// Paths of the files that failed verification
+export interface VerifyReport {
+	missing: string[];
+	corrupted: string[];
+}
+
+export type InstallPhase =
+	| "manifest"
+	| "client_json"
+	| "assets"
+	| "libraries"
+	| "natives"
+	| "java"
+	| "loader";
+
+// Totals are 0 when they aren't known
+export interface Progress {
+	filesDone: number;
+	filesTotal: number;
+	bytesDone: number;
+	bytesTotal: number;
+}
+
+// Emitted as "install-progress" while an installation is created
+export type InstallProgress = { name: string } & (
+	| { kind: "phase_started"; phase: InstallPhase; progress: Progress }
+	| { kind: "file_done"; phase: InstallPhase; path: string; size: number; progress: Progress }
+	| { kind: "phase_finished"; phase: InstallPhase; progress: Progress }
+);
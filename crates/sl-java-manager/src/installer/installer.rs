@@ -2,7 +2,7 @@ use std::{fs, path::PathBuf};
 
 use serde_json::Value;
 use sl_utils::utils::{
-    download::download_file,
+    download::{download_file, get_as_bytes, Expected},
     errors::{BackendError, JavaError},
     platform::{default_install_path, get_arch, get_os},
 };
@@ -32,7 +32,7 @@ pub async fn install_version(
         architecture={arch}&image_type={package_type}&os={os}&vendor=eclipse"
     );
 
-    let response = reqwest::get(&url).await?.json::<Value>().await?;
+    let response: Value = serde_json::from_slice(&get_as_bytes(&url).await?)?;
     let assets = response.as_array().unwrap();
     let asset = assets.first().unwrap();
 
@@ -43,6 +43,10 @@ pub async fn install_version(
 
     let download_url = package["link"].as_str().unwrap();
     let package_name = package["name"].as_str().unwrap();
+    let mut expected = Expected::new(None, package["size"].as_u64());
+    if let Some(checksum) = package["checksum"].as_str() {
+        expected = expected.sha256(checksum);
+    }
 
     let install_path = path.unwrap_or_else(|| default_install_path(&package_type));
     let java_home = install_path.join(format!("{}-{}", package_type, semver));
@@ -56,7 +60,7 @@ pub async fn install_version(
     println!("Downloading {}...", package_name);
     let temp_dir = TempDir::new()?;
     let download_path = temp_dir.path().join(package_name);
-    download_file(download_url, &download_path).await?;
+    download_file(download_url, &download_path, &expected).await?;
 
     println!("Extracting package...");
     extract_package(&download_path, &java_home)?;
@@ -1,5 +1,10 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
 
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 pub mod fabric;
@@ -14,25 +19,126 @@ pub enum OsName {
     Osx,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
-#[serde(rename_all = "snake_case")]
+/// An architecture as written in rules, the ones we don't know about are kept as [`Arch::Other`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Hash, Clone)]
+#[serde(from = "String", into = "String")]
 pub enum Arch {
     X86,
     X86_64,
-    #[serde(rename = "arm64")]
+    ARM32,
     ARM64,
+    RiscV64,
+    Other(String),
+}
+
+impl Arch {
+    pub fn name(&self) -> &str {
+        match self {
+            Arch::X86 => "x86",
+            Arch::X86_64 => "x86_64",
+            Arch::ARM32 => "arm32",
+            Arch::ARM64 => "arm64",
+            Arch::RiscV64 => "riscv64",
+            Arch::Other(name) => name,
+        }
+    }
+
+    /// What `${arch}` stands for in native classifiers, e.g. `natives-windows-${arch}`.
+    pub fn bits(&self) -> &'static str {
+        match self {
+            Arch::X86 | Arch::ARM32 => "32",
+            Arch::X86_64 | Arch::ARM64 | Arch::RiscV64 => "64",
+            Arch::Other(name) if name.contains("64") => "64",
+            Arch::Other(_) => "32",
+        }
+    }
+}
+
+impl From<String> for Arch {
+    fn from(name: String) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "x86" | "i386" | "i686" => Arch::X86,
+            "x86_64" | "amd64" | "x64" => Arch::X86_64,
+            "arm32" | "arm" => Arch::ARM32,
+            "arm64" | "aarch64" => Arch::ARM64,
+            "riscv64" => Arch::RiscV64,
+            _ => Arch::Other(name),
+        }
+    }
+}
+
+impl From<Arch> for String {
+    fn from(arch: Arch) -> Self {
+        arch.name().to_owned()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Os {
     pub name: Option<OsName>,
     pub arch: Option<Arch>,
+    /// A regex the OS version has to match, e.g. `^10\.` for Windows 10
+    pub version: Option<String>,
 }
 
 impl Os {
     pub fn matches(&self) -> bool {
-        (self.name.is_none() || self.name == Some(crate::OS))
-            && (self.arch.is_none() || self.arch == Some(crate::ARCH))
+        self.matches_platform(Platform::current())
+    }
+
+    pub fn matches_platform(&self, platform: &Platform) -> bool {
+        self.name.as_ref().is_none_or(|name| *name == platform.os)
+            && self.arch.as_ref().is_none_or(|arch| *arch == platform.arch)
+            && self
+                .version
+                .as_ref()
+                .is_none_or(|version| version_matches(version, &platform.version))
+    }
+}
+
+/// Whether the OS `version` matches the `pattern` of a rule. The patterns are compiled once,
+/// the library rules are checked for every library on every launch.
+fn version_matches(pattern: &str, version: &str) -> bool {
+    static PATTERNS: LazyLock<Mutex<HashMap<String, Option<Regex>>>> =
+        LazyLock::new(Default::default);
+
+    let mut patterns = PATTERNS.lock().unwrap();
+    let regex = patterns
+        .entry(pattern.to_owned())
+        .or_insert_with(|| Regex::new(pattern).ok());
+
+    match regex {
+        Some(regex) => regex.is_match(version),
+        // An invalid pattern matches nothing, like in the official launcher
+        None => false,
+    }
+}
+
+/// What rules are checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    pub os: OsName,
+    pub arch: Arch,
+    /// e.g. `10.0.19045` on Windows or `14.2.1` on macOS
+    pub version: String,
+}
+
+impl Platform {
+    pub fn new(os: OsName, arch: Arch, version: impl Into<String>) -> Self {
+        Self {
+            os,
+            arch,
+            version: version.into(),
+        }
+    }
+
+    /// The platform the launcher runs on.
+    pub fn current() -> &'static Platform {
+        static CURRENT: LazyLock<Platform> = LazyLock::new(|| {
+            Platform::new(crate::OS, crate::ARCH, os_info::get().version().to_string())
+        });
+
+        &CURRENT
     }
 }
 
@@ -2,7 +2,7 @@ use std::{collections::HashMap, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use super::{version_manifest::VersionType, JavaClassName, Os, OsName};
+use super::{version_manifest::VersionType, Arch, JavaClassName, Os, OsName, Platform};
 
 #[derive(Debug, Deserialize)]
 pub struct AssetObject {
@@ -13,6 +13,12 @@ pub struct AssetObject {
 #[derive(Debug, Deserialize)]
 pub struct AssetIndex {
     pub objects: HashMap<String, AssetObject>,
+    /// The `legacy` index, whose objects are read by name from `assets/virtual/legacy`
+    #[serde(default, rename = "virtual")]
+    pub r#virtual: bool,
+    /// The `pre-1.6` index, whose objects are read by name from the `resources` of the instance
+    #[serde(default)]
+    pub map_to_resources: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
@@ -29,14 +35,50 @@ pub struct Rule {
     pub os: Option<Os>,
 }
 
+/// What the launch uses, checked against the `features` of rules.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LaunchFeatures {
+    pub is_demo_user: bool,
+    pub has_custom_resolution: bool,
+    pub has_quick_plays_support: bool,
+    pub is_quick_play_singleplayer: bool,
+    pub is_quick_play_multiplayer: bool,
+    pub is_quick_play_realms: bool,
+}
+
+impl LaunchFeatures {
+    /// Whether the launch uses `feature`, features we don't know about are never used.
+    pub fn get(&self, feature: &str) -> bool {
+        match feature {
+            "is_demo_user" => self.is_demo_user,
+            "has_custom_resolution" => self.has_custom_resolution,
+            "has_quick_plays_support" => self.has_quick_plays_support,
+            "is_quick_play_singleplayer" => self.is_quick_play_singleplayer,
+            "is_quick_play_multiplayer" => self.is_quick_play_multiplayer,
+            "is_quick_play_realms" => self.is_quick_play_realms,
+            _ => false,
+        }
+    }
+}
+
 impl Rule {
-    fn matches(&self) -> bool {
-        (self.os.is_none() || self.os.as_ref().is_some_and(|os| os.matches()))
-            && self.features.is_none()
+    fn matches(&self, platform: &Platform, features: &LaunchFeatures) -> bool {
+        self.os
+            .as_ref()
+            .is_none_or(|os| os.matches_platform(platform))
+            && self.features.as_ref().is_none_or(|required| {
+                required
+                    .iter()
+                    .all(|(feature, value)| features.get(feature) == *value)
+            })
+    }
+
+    pub fn is_allowed(&self, features: &LaunchFeatures) -> bool {
+        self.is_allowed_on(Platform::current(), features)
     }
 
-    pub fn is_allowed(&self) -> bool {
-        let matched = self.matches();
+    pub fn is_allowed_on(&self, platform: &Platform, features: &LaunchFeatures) -> bool {
+        let matched = self.matches(platform, features);
         match self.action {
             RuleActionType::Allow => matched,
             RuleActionType::Disallow => !matched,
@@ -76,11 +118,11 @@ pub enum Argument {
 }
 
 impl Argument {
-    fn into_raw(self) -> Vec<String> {
+    fn into_raw(self, features: &LaunchFeatures) -> Vec<String> {
         match self {
             Argument::Arg(arg) => vec![arg],
             Argument::Rule { rules, value } => {
-                if rules.iter().all(Rule::is_allowed) {
+                if rules.iter().all(|rule| rule.is_allowed(features)) {
                     match value {
                         ArgumentValue::Value(value) => vec![value],
                         ArgumentValue::Values(values) => values,
@@ -127,12 +169,19 @@ impl Arguments {
         }
     }
 
-    pub fn into_raw(self) -> (Vec<String>, Vec<String>) {
+    /// The JVM and game arguments whose rules apply to this platform and `features`.
+    pub fn into_raw(self, features: &LaunchFeatures) -> (Vec<String>, Vec<String>) {
         match self {
             Arguments::Args { game, jvm } => {
-                let jvm: Vec<String> = jvm.into_iter().map(Argument::into_raw).flatten().collect();
+                let jvm: Vec<String> = jvm
+                    .into_iter()
+                    .flat_map(|arg| arg.into_raw(features))
+                    .collect();
 
-                let game = game.into_iter().map(Argument::into_raw).flatten().collect();
+                let game = game
+                    .into_iter()
+                    .flat_map(|arg| arg.into_raw(features))
+                    .collect();
                 (jvm, game)
             }
             Arguments::MinecraftArgs(args) => {
@@ -180,26 +229,55 @@ pub struct Library {
 }
 
 impl Library {
-    pub fn is_allowed(&self) -> bool {
-        self.rules.is_none()
-            || self
-                .rules
-                .as_ref()
-                .is_some_and(|rules| rules.iter().all(Rule::is_allowed))
+    pub fn is_allowed(&self, features: &LaunchFeatures) -> bool {
+        self.is_allowed_on(Platform::current(), features)
+    }
+
+    pub fn is_allowed_on(&self, platform: &Platform, features: &LaunchFeatures) -> bool {
+        self.rules.as_ref().is_none_or(|rules| {
+            rules
+                .iter()
+                .all(|rule| rule.is_allowed_on(platform, features))
+        })
     }
 
     pub fn native_from_platform(&self) -> Option<&Download> {
-        let natives = self.natives.as_ref()?;
+        let platform = Platform::current();
+        self.native_for(&platform.os, &platform.arch)
+    }
+
+    /// The natives jar for `os`, with `${arch}` in the classifier resolved for `arch`.
+    pub fn native_for(&self, os: &OsName, arch: &Arch) -> Option<&Download> {
+        let native = self.natives.as_ref()?.get(os)?;
         let classifiers = self.downloads.classifiers.as_ref()?;
-        
-        let mut results = natives
-            .iter()
-            .filter(|(os, _)| **os == crate::OS)
-            .map(|(_, native)| classifiers.get(native).unwrap());
-        results.next()
+
+        classifiers.get(&native.replace("${arch}", arch.bits()))
     }
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoggingFile {
+    /// e.g. `client-1.12.xml`
+    pub id: String,
+    #[serde(flatten)]
+    pub download: Download,
+}
+
+/// How the game is told where its log config is.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoggingConfig {
+    /// e.g. `-Dlog4j.configurationFile=${path}`
+    pub argument: String,
+    pub file: LoggingFile,
+    /// e.g. `log4j2-xml`
+    pub r#type: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Logging {
+    pub client: Option<LoggingConfig>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Client {
@@ -214,10 +292,23 @@ pub struct Client {
     pub id: String,
     pub release_time: String,
     pub r#type: VersionType,
+    /// Versions before log4j (13w39a) have none
+    pub logging: Option<Logging>,
 }
 
 impl Client {
+    pub fn logging_config(&self) -> Option<&LoggingConfig> {
+        self.logging.as_ref()?.client.as_ref()
+    }
+
+    /// The libraries of this platform, library rules only ever depend on the platform.
     pub fn libraries(&self) -> impl Iterator<Item = &Library> {
-        self.libraries.iter().filter(|x| x.is_allowed())
+        self.libraries_on(Platform::current())
+    }
+
+    pub fn libraries_on<'a>(&'a self, platform: &'a Platform) -> impl Iterator<Item = &'a Library> {
+        self.libraries
+            .iter()
+            .filter(|x| x.is_allowed_on(platform, &LaunchFeatures::default()))
     }
 }
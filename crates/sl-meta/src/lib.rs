@@ -18,6 +18,10 @@ pub const ARCH: Arch = if cfg!(target_arch = "x86") {
     Arch::X86_64
 } else if cfg!(target_arch = "aarch64") {
     Arch::ARM64
+} else if cfg!(target_arch = "arm") {
+    Arch::ARM32
+} else if cfg!(target_arch = "riscv64") {
+    Arch::RiscV64
 } else {
     panic!("Unknown Arch!")
 };
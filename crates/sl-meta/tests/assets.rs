@@ -0,0 +1,20 @@
+use sl_meta::json::vanilla::AssetIndex;
+
+fn index(json: &str) -> AssetIndex {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn asset_index_layouts() {
+    let objects = r#""objects": {"icons/icon_16x16.png": {"hash": "bdf48ef6b5d0d23bbb02e17d04865216179f510a", "size": 3665}}"#;
+
+    let modern = index(&format!("{{{objects}}}"));
+    assert!(!modern.r#virtual && !modern.map_to_resources);
+
+    let legacy = index(&format!(r#"{{"virtual": true, {objects}}}"#));
+    assert!(legacy.r#virtual && !legacy.map_to_resources);
+    assert_eq!(legacy.objects["icons/icon_16x16.png"].size, 3665);
+
+    let pre_1_6 = index(&format!(r#"{{"map_to_resources": true, {objects}}}"#));
+    assert!(pre_1_6.map_to_resources);
+}
@@ -0,0 +1,10 @@
+use sl_meta::json::vanilla::Client;
+
+/// The version JSONs in `tests/fixtures`, trimmed down to their arguments and a few libraries.
+pub fn fixture(version: &str) -> Client {
+    let path = format!(
+        "{}/tests/fixtures/{version}.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::fixture;
+
+#[test]
+fn logging_config() {
+    let client = fixture("1.20.4");
+    let config = client.logging_config().unwrap();
+
+    assert_eq!(config.argument, "-Dlog4j.configurationFile=${path}");
+    assert_eq!(config.r#type, "log4j2-xml");
+    assert_eq!(config.file.id, "client-1.12.xml");
+    assert_eq!(config.file.download.size, Some(888));
+    assert!(config.file.download.path.is_none());
+
+    // From before log4j
+    assert!(fixture("1.6.4").logging_config().is_none());
+}
@@ -0,0 +1,49 @@
+use serde_json::json;
+use sl_meta::json::{vanilla::Library, Arch, OsName};
+
+fn lwjgl_platform() -> Library {
+    serde_json::from_value(json!({
+        "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+        "downloads": {
+            "classifiers": {
+                "natives-linux": {
+                    "path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar",
+                    "url": "https://libraries.minecraft.net/natives-linux.jar"
+                },
+                "natives-windows-32": {
+                    "url": "https://libraries.minecraft.net/natives-windows-32.jar"
+                },
+                "natives-windows-64": {
+                    "url": "https://libraries.minecraft.net/natives-windows-64.jar"
+                }
+            }
+        },
+        "extract": { "exclude": ["META-INF/"] },
+        "natives": {
+            "linux": "natives-linux",
+            "windows": "natives-windows-${arch}"
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn native_classifiers() {
+    let lib = lwjgl_platform();
+
+    let url = |os, arch| lib.native_for(&os, &arch).map(|native| native.url.as_str());
+
+    assert_eq!(
+        url(OsName::Linux, Arch::X86_64),
+        Some("https://libraries.minecraft.net/natives-linux.jar")
+    );
+    assert_eq!(
+        url(OsName::Windows, Arch::X86),
+        Some("https://libraries.minecraft.net/natives-windows-32.jar")
+    );
+    assert_eq!(
+        url(OsName::Windows, Arch::X86_64),
+        Some("https://libraries.minecraft.net/natives-windows-64.jar")
+    );
+    assert_eq!(url(OsName::Osx, Arch::ARM64), None);
+}
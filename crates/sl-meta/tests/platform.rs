@@ -0,0 +1,132 @@
+mod common;
+
+use common::fixture;
+use serde_json::json;
+use sl_meta::json::{vanilla::Client, Arch, Os, OsName, Platform};
+
+fn library_names(client: &Client, platform: &Platform) -> Vec<String> {
+    client
+        .libraries_on(platform)
+        .map(|lib| serde_json::to_value(&lib.name).unwrap())
+        .map(|name| name.as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn every_fixture_parses() {
+    for version in ["1.6.4", "1.8.9", "1.20.4"] {
+        assert_eq!(fixture(version).id, version);
+    }
+}
+
+#[test]
+fn unknown_architectures() {
+    let arch = |name: &str| serde_json::from_value::<Arch>(json!(name)).unwrap();
+
+    assert_eq!(arch("arm32"), Arch::ARM32);
+    assert_eq!(arch("aarch64"), Arch::ARM64);
+    assert_eq!(arch("riscv64"), Arch::RiscV64);
+    assert_eq!(arch("loongarch64"), Arch::Other("loongarch64".to_owned()));
+    assert_eq!(json!(arch("loongarch64")), json!("loongarch64"));
+
+    assert_eq!(arch("x86").bits(), "32");
+    assert_eq!(arch("arm32").bits(), "32");
+    assert_eq!(arch("riscv64").bits(), "64");
+    assert_eq!(arch("loongarch64").bits(), "64");
+}
+
+#[test]
+fn arch_rules() {
+    let os: Os = serde_json::from_value(json!({ "arch": "riscv64" })).unwrap();
+
+    assert!(os.matches_platform(&Platform::new(OsName::Linux, Arch::RiscV64, "6.8")));
+    assert!(!os.matches_platform(&Platform::new(OsName::Linux, Arch::X86_64, "6.8")));
+}
+
+#[test]
+fn version_regex() {
+    let os: Os = serde_json::from_value(json!({
+        "name": "windows",
+        "version": "^10\\."
+    }))
+    .unwrap();
+
+    assert!(os.matches_platform(&Platform::new(OsName::Windows, Arch::X86_64, "10.0.19045")));
+    assert!(!os.matches_platform(&Platform::new(OsName::Windows, Arch::X86_64, "6.1.7601")));
+    assert!(!os.matches_platform(&Platform::new(OsName::Linux, Arch::X86_64, "10.0")));
+}
+
+#[test]
+fn invalid_version_regex() {
+    let os: Os = serde_json::from_value(json!({
+        "name": "windows",
+        "version": "^10\\.(["
+    }))
+    .unwrap();
+
+    // Checked twice, the second time the pattern comes from the cache
+    for _ in 0..2 {
+        assert!(!os.matches_platform(&Platform::new(OsName::Windows, Arch::X86_64, "10.0.19045")));
+    }
+}
+
+#[test]
+fn old_macos_libraries() {
+    let client = fixture("1.6.4");
+
+    let leopard = library_names(&client, &Platform::new(OsName::Osx, Arch::X86_64, "10.5.8"));
+    assert!(leopard.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.1-nightly-20130708-debug3".to_owned()));
+    assert!(!leopard.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.0".to_owned()));
+
+    let mavericks = library_names(&client, &Platform::new(OsName::Osx, Arch::X86_64, "10.9.5"));
+    assert!(mavericks.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.0".to_owned()));
+    assert!(mavericks.contains(&"org.lwjgl.lwjgl:lwjgl-platform:2.9.0".to_owned()));
+    assert!(!mavericks.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.1-nightly-20130708-debug3".to_owned()));
+}
+
+#[test]
+fn natives_arch_substitution() {
+    let client = fixture("1.8.9");
+    let twitch = |platform: &Platform| {
+        client
+            .libraries_on(platform)
+            .find(|lib| lib.natives.is_some())
+            .and_then(|lib| lib.native_for(&platform.os, &platform.arch))
+            .map(|native| native.url.rsplit('-').next().unwrap().to_owned())
+    };
+
+    let windows = |arch| Platform::new(OsName::Windows, arch, "10.0");
+    assert_eq!(twitch(&windows(Arch::X86)), Some("32.jar".to_owned()));
+    assert_eq!(twitch(&windows(Arch::X86_64)), Some("64.jar".to_owned()));
+    assert_eq!(twitch(&windows(Arch::ARM64)), Some("64.jar".to_owned()));
+    assert_eq!(
+        twitch(&Platform::new(OsName::Osx, Arch::ARM64, "14.2")),
+        Some("osx.jar".to_owned())
+    );
+    assert_eq!(
+        twitch(&Platform::new(OsName::Linux, Arch::X86_64, "6.8")),
+        None
+    );
+}
+
+#[test]
+fn modern_libraries_by_os() {
+    let client = fixture("1.20.4");
+    // Natives are only told apart by their classifier, which isn't part of the name
+    let jars = |platform: &Platform| {
+        client
+            .libraries_on(platform)
+            .filter_map(|lib| lib.downloads.artifact.as_ref()?.path.as_ref())
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let linux = jars(&Platform::new(OsName::Linux, Arch::X86_64, "6.8"));
+    let macos = jars(&Platform::new(OsName::Osx, Arch::ARM64, "14.2"));
+
+    assert!(linux.contains(&"lwjgl-3.3.2-natives-linux.jar".to_owned()));
+    assert!(!linux.contains(&"lwjgl-3.3.2-natives-windows.jar".to_owned()));
+    assert!(!linux.contains(&"java-objc-bridge-1.1.jar".to_owned()));
+    assert!(macos.contains(&"java-objc-bridge-1.1.jar".to_owned()));
+    assert!(!macos.contains(&"lwjgl-3.3.2-natives-linux.jar".to_owned()));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::fixture;
+use sl_meta::json::vanilla::LaunchFeatures;
+
+fn game_args(version: &str, features: LaunchFeatures) -> Vec<String> {
+    fixture(version).arguments.into_raw(&features).1
+}
+
+fn contains(args: &[String], expected: &[&str]) -> bool {
+    args.windows(expected.len())
+        .any(|window| window == expected)
+}
+
+#[test]
+fn feature_arguments_are_dropped_by_default() {
+    let args = game_args("1.20.4", LaunchFeatures::default());
+
+    assert_eq!(args.len(), 22);
+    assert!(!args
+        .iter()
+        .any(|arg| arg == "--demo" || arg.starts_with("--quickPlay")));
+    assert!(!contains(&args, &["--width"]));
+}
+
+#[test]
+fn demo_and_resolution() {
+    let args = game_args(
+        "1.20.4",
+        LaunchFeatures {
+            is_demo_user: true,
+            has_custom_resolution: true,
+            ..Default::default()
+        },
+    );
+
+    assert!(contains(&args, &["--demo"]));
+    assert!(contains(
+        &args,
+        &[
+            "--width",
+            "${resolution_width}",
+            "--height",
+            "${resolution_height}"
+        ]
+    ));
+    assert!(!args.iter().any(|arg| arg.starts_with("--quickPlay")));
+}
+
+#[test]
+fn quick_play_multiplayer() {
+    let args = game_args(
+        "1.20.4",
+        LaunchFeatures {
+            has_quick_plays_support: true,
+            is_quick_play_multiplayer: true,
+            ..Default::default()
+        },
+    );
+
+    assert!(contains(&args, &["--quickPlayPath", "${quickPlayPath}"]));
+    assert!(contains(
+        &args,
+        &["--quickPlayMultiplayer", "${quickPlayMultiplayer}"]
+    ));
+    assert!(!contains(&args, &["--quickPlaySingleplayer"]));
+    assert!(!contains(&args, &["--quickPlayRealms"]));
+    assert!(!contains(&args, &["--demo"]));
+}
+
+#[test]
+fn unknown_features_never_match() {
+    assert!(!LaunchFeatures::default().get("is_demo_user"));
+    assert!(!LaunchFeatures {
+        is_demo_user: true,
+        ..Default::default()
+    }
+    .get("has_future_feature"));
+}
+
+#[test]
+fn legacy_arguments_have_no_rules() {
+    let (jvm, game) = fixture("1.8.9")
+        .arguments
+        .into_raw(&LaunchFeatures::default());
+
+    assert!(contains(&jvm, &["-cp", "${classpath}"]));
+    assert!(contains(&game, &["--userProperties", "${user_properties}"]));
+}
+
+#[test]
+fn library_rules() {
+    let names = |version| {
+        fixture(version)
+            .libraries()
+            .map(|lib| serde_json::to_value(&lib.name).unwrap())
+            .map(|name| name.as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let modern = names("1.20.4");
+    let legacy = names("1.8.9");
+
+    assert!(modern.contains(&"com.mojang:brigadier:1.2.9".to_owned()));
+    assert_eq!(
+        modern.contains(&"ca.weblite:java-objc-bridge:1.1".to_owned()),
+        cfg!(target_os = "macos")
+    );
+    assert_eq!(
+        legacy.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209".to_owned()),
+        !cfg!(target_os = "macos")
+    );
+    assert_eq!(
+        legacy.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.2-nightly-20140822".to_owned()),
+        cfg!(target_os = "macos")
+    );
+}
@@ -1,10 +1,17 @@
-use std::{fs::File, io::Write, path::PathBuf};
-use reqwest::Client;
+use std::path::PathBuf;
 use serde::Deserialize;
+use sl_utils::utils::download::{download_file, get_as_bytes, Expected};
+
+#[derive(Debug, Deserialize)]
+pub struct ModrinthHashes {
+    pub sha1: Option<String>,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct ModrinthFile {
     pub url: String,
+    pub hashes: ModrinthHashes,
+    pub size: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,24 +24,15 @@ pub struct ProjectDownload {
 pub async fn install_modrinth_file(id: &str, dest: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     let url = format!("https://api.modrinth.com/v2/project/{}/version", id);
 
-    let client = Client::new();
-
-    let response = client.get(&url).send().await?;
+    let versions: Vec<ProjectDownload> = serde_json::from_slice(&get_as_bytes(&url).await?)?;
 
-    let versions = response.json::<Vec<ProjectDownload>>().await?;
-
-    let file_url = &versions[0].files[0].url;
+    let file = &versions[0].files[0];
+    let file_url = &file.url;
 
     println!("{:?}", file_url);
 
-    let mut resp = client.get(file_url).send().await?;
-
-    println!("{:?}", resp);
-    let mut file = File::create(dest.join("some.jar"))?;
-
-    while let Some(chunk) = resp.chunk().await? {
-        file.write_all(&chunk)?;
-    }
+    let expected = Expected::new(file.hashes.sha1.as_deref(), file.size);
+    download_file(file_url, &dest.join("some.jar"), &expected).await?;
 
     Ok(())
 }
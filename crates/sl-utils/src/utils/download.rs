@@ -1,32 +1,426 @@
-use std::{fs::File, io::Write, path::Path};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, RwLock},
+    time::Duration,
+};
 
 use bytes::Bytes;
+use reqwest::{header, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard, Semaphore};
 
-use super::errors::{BackendError, DownloadError};
+use super::{
+    errors::{BackendError, DownloadError},
+    http,
+    mirrors::Mirrors,
+};
+
+/// How many downloads run at once across the whole launcher.
+pub const MAX_CONCURRENT_DOWNLOADS: usize = 16;
+
+static DOWNLOADER: LazyLock<RwLock<Arc<Downloader>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Downloader::default())));
+
+/// The lock of every path currently locked or waited on, see [`lock_file`].
+static FILE_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<AsyncMutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+/// Size and hashes a downloaded file must have, each is only checked when known.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Expected {
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+}
+
+impl Expected {
+    pub fn new(sha1: Option<&str>, size: Option<u64>) -> Self {
+        Self {
+            sha1: sha1.map(str::to_owned),
+            size,
+            sha256: None,
+        }
+    }
+
+    /// For the files published with a SHA-256 rather than a SHA-1, e.g. Java packages.
+    pub fn sha256(mut self, sha256: &str) -> Self {
+        self.sha256 = Some(sha256.to_owned());
+        self
+    }
+
+    /// Whether nothing is known about the file, so a complete one can't be told apart from a truncated one.
+    fn is_unknown(&self) -> bool {
+        self.sha1.is_none() && self.size.is_none() && self.sha256.is_none()
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.size.is_none_or(|size| data.len() as u64 == size)
+            && self
+                .sha1
+                .as_ref()
+                .is_none_or(|sha1| sha1_hex(data).eq_ignore_ascii_case(sha1))
+            && self
+                .sha256
+                .as_ref()
+                .is_none_or(|sha256| hex(&Sha256::digest(data)).eq_ignore_ascii_case(sha256))
+    }
+
+    fn matches_file(&self, path: &Path) -> io::Result<bool> {
+        if self
+            .size
+            .is_some_and(|size| fs::metadata(path).map_or(true, |m| m.len() != size))
+        {
+            return Ok(false);
+        }
+
+        if let Some(ref sha1) = self.sha1 {
+            let mut hasher = Sha1::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            if !hex(&hasher.finalize()).eq_ignore_ascii_case(sha1) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(ref sha256) = self.sha256 {
+            let mut hasher = Sha256::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            if !hex(&hasher.finalize()).eq_ignore_ascii_case(sha256) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+pub fn sha1_hex(data: &[u8]) -> String {
+    hex(&Sha1::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// What identifies the version of a cached response, sent back to only download it again once it changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Downloads files with retries and resuming, sharing one concurrency limit between everything it downloads.
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    client: Client,
+    permits: Arc<Semaphore>,
+    mirrors: Mirrors,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self::new(http::client(), MAX_CONCURRENT_DOWNLOADS)
+    }
+}
+
+impl Downloader {
+    pub fn new(client: Client, max_concurrent: usize) -> Self {
+        Self {
+            client,
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            mirrors: Mirrors::default(),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// The downloader shared by the whole launcher.
+    pub fn global() -> Arc<Downloader> {
+        DOWNLOADER.read().unwrap().clone()
+    }
+
+    /// Replaces the downloader shared by the whole launcher, e.g. once its config is read.
+    pub fn set_global(downloader: Downloader) {
+        *DOWNLOADER.write().unwrap() = Arc::new(downloader);
+    }
+
+    pub fn mirrors(mut self, mirrors: Mirrors) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// How many times a download is retried after a transient error.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// How long to wait before the first retry, doubling with every retry.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    async fn retry<T, F>(&self, mut attempt: impl FnMut() -> F) -> Result<T, DownloadError>
+    where
+        F: Future<Output = Result<T, DownloadError>>,
+    {
+        let mut tries = 0;
+
+        loop {
+            let result = {
+                let _permit = self.permits.acquire().await.unwrap();
+                attempt().await
+            };
+
+            match result {
+                Err(err) if err.is_transient() && tries < self.retries => {
+                    tokio::time::sleep(self.backoff * 2u32.pow(tries)).await;
+                    tries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Retries `attempt` on every mirror of `url` in turn, until one succeeds.
+    async fn with_mirrors<T, F>(
+        &self,
+        url: &str,
+        mut attempt: impl FnMut(String) -> F,
+    ) -> Result<T, DownloadError>
+    where
+        F: Future<Output = Result<T, DownloadError>>,
+    {
+        if http::is_offline() {
+            return Err(DownloadError::Offline(url.to_owned()));
+        }
+
+        let mut candidates = self.mirrors.candidates(url).into_iter().peekable();
+
+        loop {
+            let candidate = candidates.next().unwrap();
+
+            match self.retry(|| attempt(candidate.clone())).await {
+                // A mirror may well not have a file the origin has, so any status falls back
+                Err(err) if !matches!(err, DownloadError::IO(_)) && candidates.peek().is_some() => {
+                    continue
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Downloads `url` into memory, for small files such as metadata.
+    pub async fn get_bytes(&self, url: &str) -> Result<Bytes, DownloadError> {
+        self.with_mirrors(url, |url| async move {
+            let response = self.client.get(url).send().await?;
+            if !response.status().is_success() {
+                return Err(DownloadError::Status(response.status()));
+            }
+
+            Ok(response.bytes().await?)
+        })
+        .await
+    }
+
+    /// Downloads `url` unless it didn't change since it was `cached`, returning `None` then.
+    pub async fn get_if_modified(
+        &self,
+        url: &str,
+        cached: &Validators,
+    ) -> Result<Option<(Bytes, Validators)>, DownloadError> {
+        self.with_mirrors(url, |url| async move {
+            let mut request = self.client.get(url);
+            if let Some(ref etag) = cached.etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            if let Some(ref last_modified) = cached.last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+            }
+
+            let response = request.send().await?;
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(None);
+            }
+            if !response.status().is_success() {
+                return Err(DownloadError::Status(response.status()));
+            }
+
+            let header_value = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_owned)
+            };
+            let validators = Validators {
+                etag: header_value(header::ETAG),
+                last_modified: header_value(header::LAST_MODIFIED),
+            };
+
+            Ok(Some((response.bytes().await?, validators)))
+        })
+        .await
+    }
+
+    /// Like [`Downloader::get_bytes`], failing if the data isn't what's `expected`.
+    pub async fn get_verified(
+        &self,
+        url: &str,
+        expected: &Expected,
+    ) -> Result<Bytes, DownloadError> {
+        self.with_mirrors(url, |url| async move {
+            let response = self.client.get(&url).send().await?;
+            if !response.status().is_success() {
+                return Err(DownloadError::Status(response.status()));
+            }
+
+            let data = response.bytes().await?;
+            if !expected.matches(&data) {
+                return Err(DownloadError::Corrupted(url));
+            }
+
+            Ok(data)
+        })
+        .await
+    }
+
+    /// Streams `url` to `path` through a `.part` file renamed once complete and verified,
+    /// resuming what a previous attempt left in it. Returns the size of the file.
+    pub async fn download(
+        &self,
+        url: &str,
+        path: &Path,
+        expected: &Expected,
+    ) -> Result<u64, DownloadError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let part = &part_path(path);
+        self.with_mirrors(url, |url| async move {
+            self.download_part(&url, part, expected).await
+        })
+        .await?;
+
+        fs::rename(part, path)?;
+        Ok(fs::metadata(path)?.len())
+    }
+
+    async fn download_part(
+        &self,
+        url: &str,
+        part: &Path,
+        expected: &Expected,
+    ) -> Result<(), DownloadError> {
+        // Without a size or hash to check it against, what's left may well be a truncated file
+        let resume_from = if expected.is_unknown() {
+            0
+        } else {
+            fs::metadata(part).map_or(0, |metadata| metadata.len())
+        };
+
+        let mut request = self.client.get(url);
+        if resume_from > 0 {
+            request = request.header(header::RANGE, format!("bytes={resume_from}-"));
+        }
+
+        let mut response = request.send().await?;
+        let mut file = match response.status() {
+            StatusCode::PARTIAL_CONTENT => OpenOptions::new().append(true).open(part)?,
+            // Nothing left past what we have, it's either complete or bigger than the actual file
+            StatusCode::RANGE_NOT_SATISFIABLE => return verify_part(url, part, expected),
+            status if status.is_success() => File::create(part)?,
+            status => return Err(DownloadError::Status(status)),
+        };
+
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+        }
+        file.flush()?;
+
+        verify_part(url, part, expected)
+    }
+}
+
+/// Deletes `part` if it isn't what's `expected`, so the next attempt starts over.
+fn verify_part(url: &str, part: &Path, expected: &Expected) -> Result<(), DownloadError> {
+    if expected.matches_file(part)? {
+        return Ok(());
+    }
+
+    fs::remove_file(part)?;
+    Err(DownloadError::Corrupted(url.to_owned()))
+}
+
+/// Holds the lock of a path until dropped, see [`lock_file`].
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let mut locks = FILE_LOCKS.lock().unwrap();
+        self.guard.take();
+
+        // Only the map is left holding it, nobody else waits on that path
+        if locks
+            .get(&self.path)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(&self.path);
+        }
+    }
+}
+
+/// Waits until no other task of the launcher holds `path`, then holds it until the returned lock is dropped.
+/// Lets instances installing at the same time share libraries and assets, only one of them downloading each.
+pub async fn lock_file(path: &Path) -> FileLock {
+    let lock = FILE_LOCKS
+        .lock()
+        .unwrap()
+        .entry(path.to_path_buf())
+        .or_default()
+        .clone();
+
+    FileLock {
+        path: path.to_path_buf(),
+        guard: Some(lock.lock_owned().await),
+    }
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
 
 /*
     For SynthLauncher Core
 */
 pub async fn get_as_bytes(url: &str) -> Result<Bytes, DownloadError> {
-    let res = reqwest::get(url).await?;
-    if !res.status().is_success() {
-        return Err(DownloadError::Status(res.status()));
-    }
-
-    let bytes = res.bytes().await?;
-    Ok(bytes)
+    Downloader::global().get_bytes(url).await
 }
 
 /*
     For Java Manager
 */
-pub async fn download_file(url: &str, path: &Path) -> Result<(), BackendError> {
-    let mut res = reqwest::get(url).await?;
-    let mut file = File::create(path)?;
-
-    while let Some(chunk) = res.chunk().await? {
-        file.write_all(&chunk)?;
-    }
-
+pub async fn download_file(
+    url: &str,
+    path: &Path,
+    expected: &Expected,
+) -> Result<(), BackendError> {
+    let _lock = lock_file(path).await;
+    Downloader::global().download(url, path, expected).await?;
     Ok(())
 }
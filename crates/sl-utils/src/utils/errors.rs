@@ -17,6 +17,12 @@ pub enum DownloadError {
 
     #[error("Some other request error: {0}")]
     Other(reqwest::Error),
+
+    #[error("The file downloaded from {0} doesn't match its size or SHA-1")]
+    Corrupted(String),
+
+    #[error("Can't download {0} in offline mode")]
+    Offline(String),
 }
 
 #[derive(Debug, Error)]
@@ -27,6 +33,16 @@ pub enum InstallationError {
     VersionNotFound(String),
     #[error("Installation {0} was not found")]
     InstallationNotFound(String),
+    #[error("The version manifest isn't available, go online once to download it")]
+    ManifestUnavailable,
+    #[error("Minecraft {0} can't launch into worlds or realms, only 1.20 and newer can")]
+    QuickPlayUnsupported(String),
+    #[error("Minecraft {0} has an invalid release time: {1}")]
+    InvalidReleaseTime(String, String),
+    #[error("The log config of {0} is missing or corrupted, repair the installation")]
+    LogConfigUnavailable(String),
+    #[error("The asset index names a file outside of the assets: {0}")]
+    InvalidAssetName(String),
 }
 
 #[derive(Debug, Error)]
@@ -40,7 +56,18 @@ pub enum JavaError {
 }
 
 #[derive(Debug, Error)]
-pub enum ConfigError {}
+pub enum ConfigError {
+    #[error("Invalid proxy {0}: {1}")]
+    InvalidProxy(String, reqwest::Error),
+    #[error("Invalid certificate {0}: {1}")]
+    InvalidCertificate(std::path::PathBuf, String),
+    #[error("Invalid value for {0}: {1}")]
+    InvalidValue(String, String),
+    #[error("Failed to build the HTTP client: {0}")]
+    HttpClient(reqwest::Error),
+    #[error("Failed to read the proxy password from the secret store: {0}")]
+    Secret(String),
+}
 
 #[derive(Debug, Error)]
 pub enum ZipExtractionError {
@@ -81,6 +108,21 @@ pub enum BackendError {
     InstallationError(#[from] InstallationError),
 }
 
+impl DownloadError {
+    /// Whether trying again later might succeed, e.g. a timeout or a server error.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            DownloadError::Timeout | DownloadError::Other(_) | DownloadError::Corrupted(_) => true,
+            DownloadError::Status(status) => {
+                status.is_server_error()
+                    || *status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            DownloadError::InvalidURL | DownloadError::IO(_) | DownloadError::Offline(_) => false,
+        }
+    }
+}
+
 impl From<reqwest::Error> for DownloadError {
     fn from(value: reqwest::Error) -> Self {
         if value.is_builder() {
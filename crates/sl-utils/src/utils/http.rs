@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock, RwLock,
+    },
+    time::Duration,
+};
+
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+
+use super::errors::ConfigError;
+
+pub const DEFAULT_USER_AGENT: &str = concat!("SynthLauncher/", env!("CARGO_PKG_VERSION"));
+
+static CLIENT: LazyLock<RwLock<Client>> = LazyLock::new(|| {
+    RwLock::new(
+        HttpConfig::default()
+            .build()
+            .expect("Failed to build the default HTTP client"),
+    )
+});
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Settings of the HTTP client every request of the launcher goes through.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// e.g. `http://proxy.example.com:8080`, credentials may be in the URL
+    pub proxy: Option<String>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+    /// Comma separated hosts reached without the proxy, like the `NO_PROXY` variable
+    pub no_proxy: Option<String>,
+    /// PEM or DER certificates trusted on top of the system ones
+    pub root_certificates: Vec<PathBuf>,
+    pub user_agent: Option<String>,
+    pub connect_timeout: Option<Duration>,
+    /// How long a response may stay silent, so slow but steady downloads don't time out
+    pub read_timeout: Option<Duration>,
+}
+
+impl HttpConfig {
+    pub fn build(&self) -> Result<Client, ConfigError> {
+        let mut builder = Client::builder()
+            .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .connect_timeout(self.connect_timeout.unwrap_or(Duration::from_secs(30)))
+            .read_timeout(self.read_timeout.unwrap_or(Duration::from_secs(60)));
+
+        if let Some(ref url) = self.proxy {
+            let mut proxy =
+                Proxy::all(url).map_err(|err| ConfigError::InvalidProxy(url.clone(), err))?;
+
+            if let Some(ref username) = self.proxy_username {
+                proxy = proxy.basic_auth(username, self.proxy_password.as_deref().unwrap_or(""));
+            }
+            proxy = proxy.no_proxy(self.no_proxy.as_deref().and_then(NoProxy::from_string));
+
+            builder = builder.proxy(proxy);
+        }
+
+        for path in &self.root_certificates {
+            let invalid =
+                |err: &dyn ToString| ConfigError::InvalidCertificate(path.clone(), err.to_string());
+
+            let data = fs::read(path).map_err(|err| invalid(&err))?;
+            let certificate = if data.starts_with(b"-----BEGIN") {
+                Certificate::from_pem(&data)
+            } else {
+                Certificate::from_der(&data)
+            }
+            .map_err(|err| invalid(&err))?;
+
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        builder.build().map_err(ConfigError::HttpClient)
+    }
+}
+
+/// The HTTP client shared by the whole launcher, cheap to clone.
+pub fn client() -> Client {
+    CLIENT.read().unwrap().clone()
+}
+
+/// Replaces the HTTP client shared by the whole launcher, e.g. once its config is read.
+pub fn set_client(client: Client) {
+    *CLIENT.write().unwrap() = client;
+}
+
+/// Whether the launcher stays off the network, downloads failing with [`DownloadError::Offline`].
+///
+/// [`DownloadError::Offline`]: super::errors::DownloadError::Offline
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
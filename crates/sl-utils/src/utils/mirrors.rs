@@ -0,0 +1,88 @@
+use std::{fs, io, path::Path};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+fn default_true() -> bool {
+    true
+}
+
+/// Sends the requests to `host` to its mirrors instead, trying them in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorRule {
+    /// e.g. `piston-meta.mojang.com`
+    pub host: String,
+    /// Base URLs replacing the scheme and host, e.g. `https://bmclapi2.bangbang93.com`
+    /// for `piston-meta.mojang.com` or `https://bmclapi2.bangbang93.com/maven` for `libraries.minecraft.net`
+    pub mirrors: Vec<String>,
+    /// Whether to try the original host once every mirror failed
+    #[serde(default = "default_true")]
+    pub fallback_to_origin: bool,
+}
+
+/// URL rewriting rules, read from `mirrors.json` in the launcher directory.
+///
+/// ```json
+/// {
+///     "rules": [
+///         {
+///             "host": "resources.download.minecraft.net",
+///             "mirrors": ["http://localhost:8080/assets", "https://bmclapi2.bangbang93.com/assets"]
+///         }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mirrors {
+    #[serde(default)]
+    pub rules: Vec<MirrorRule>,
+}
+
+impl Mirrors {
+    /// Reads the rules at `path`, there are none if the file doesn't exist.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: MirrorRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// The URLs to try for `url` in order, just `url` if no rule applies to its host.
+    pub fn candidates(&self, url: &str) -> Vec<String> {
+        let Ok(parsed) = Url::parse(url) else {
+            return vec![url.to_owned()];
+        };
+
+        let rule = parsed.host_str().and_then(|host| {
+            self.rules
+                .iter()
+                .find(|rule| rule.host.eq_ignore_ascii_case(host))
+        });
+        let Some(rule) = rule else {
+            return vec![url.to_owned()];
+        };
+
+        // Everything after the host, so the path of the original URL is kept
+        let authority = url.find("://").map_or(0, |start| start + 3);
+        let rest = url[authority..]
+            .find(['/', '?', '#'])
+            .map_or("", |end| &url[authority + end..]);
+
+        let mut candidates = rule
+            .mirrors
+            .iter()
+            .map(|mirror| format!("{}{rest}", mirror.trim_end_matches('/')))
+            .collect::<Vec<_>>();
+        if rule.fallback_to_origin || candidates.is_empty() {
+            candidates.push(url.to_owned());
+        }
+
+        candidates
+    }
+}
@@ -1,4 +1,6 @@
 pub mod download;
 pub mod errors;
+pub mod http;
+pub mod mirrors;
 pub mod platform;
 pub mod zip;
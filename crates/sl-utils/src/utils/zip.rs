@@ -33,9 +33,8 @@ impl<'a> ZipExtractor<'a> {
                 None => continue,
             };
 
-            if exclude.contains(&&file_path.as_path())
-                || file_path.parent().is_some_and(|p| exclude.contains(&p))
-            {
+            // Excluding a directory such as `META-INF/` excludes everything in it
+            if exclude.iter().any(|excluded| file_path.starts_with(excluded)) {
                 continue;
             }
 
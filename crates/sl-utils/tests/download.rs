@@ -0,0 +1,271 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::Client;
+use sl_utils::utils::{
+    download::{lock_file, sha1_hex, Downloader, Expected, Validators},
+    errors::DownloadError,
+};
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{header, header_exists, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const DATA: &[u8] = b"hello world";
+
+fn downloader() -> Downloader {
+    Downloader::new(Client::new(), 4).backoff(Duration::from_millis(1))
+}
+
+fn expected() -> Expected {
+    Expected::new(Some(&sha1_hex(DATA)), Some(DATA.len() as u64))
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(DATA))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("nested").join("file");
+
+    let size = downloader()
+        .download(&format!("{}/file", server.uri()), &file, &expected())
+        .await
+        .unwrap();
+    assert_eq!(size, DATA.len() as u64);
+    assert_eq!(std::fs::read(&file).unwrap(), DATA);
+    assert!(!dir.path().join("nested").join("file.part").exists());
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let err = downloader()
+        .download(
+            &format!("{}/file", server.uri()),
+            &dir.path().join("file"),
+            &expected(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DownloadError::Status(status) if status == 404));
+}
+
+#[tokio::test]
+async fn resumes_partial_download() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .and(header("range", "bytes=6-"))
+        .respond_with(ResponseTemplate::new(206).set_body_bytes(&DATA[6..]))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("file");
+    std::fs::write(dir.path().join("file.part"), &DATA[..6]).unwrap();
+
+    downloader()
+        .download(&format!("{}/file", server.uri()), &file, &expected())
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&file).unwrap(), DATA);
+}
+
+#[tokio::test]
+async fn unknown_partial_download_is_not_resumed() {
+    let server = MockServer::start().await;
+
+    // Resuming past the end of the file is answered with a 416, which would keep the leftover as is
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .and(header_exists("range"))
+        .respond_with(ResponseTemplate::new(416))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(DATA))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("file");
+    std::fs::write(dir.path().join("file.part"), b"hello world, and then some").unwrap();
+
+    downloader()
+        .download(
+            &format!("{}/file", server.uri()),
+            &file,
+            &Expected::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&file).unwrap(), DATA);
+}
+
+#[tokio::test]
+async fn sha256_is_checked() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(DATA))
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let url = format!("{}/file", server.uri());
+    let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    downloader()
+        .download(
+            &url,
+            &dir.path().join("file"),
+            &Expected::default().sha256(sha256),
+        )
+        .await
+        .unwrap();
+
+    let err = downloader()
+        .retries(0)
+        .download(
+            &url,
+            &dir.path().join("other"),
+            &Expected::default().sha256(&sha256.replace('b', "c")),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DownloadError::Corrupted(_)));
+}
+
+#[tokio::test]
+async fn corrupted_download_is_discarded() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"hello_world".as_slice()))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("file");
+
+    let err = downloader()
+        .retries(1)
+        .download(&format!("{}/file", server.uri()), &file, &expected())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DownloadError::Corrupted(_)));
+    assert!(!file.exists());
+    assert!(!dir.path().join("file.part").exists());
+}
+
+#[tokio::test]
+async fn unchanged_file_is_not_downloaded_again() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/manifest.json"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/manifest.json"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("etag", "\"v1\"")
+                .insert_header("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+                .set_body_bytes(DATA),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/manifest.json", server.uri());
+    let (data, validators) = downloader()
+        .get_if_modified(&url, &Validators::default())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&data[..], DATA);
+    assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(
+        validators.last_modified.as_deref(),
+        Some("Wed, 21 Oct 2015 07:28:00 GMT")
+    );
+
+    let unchanged = downloader()
+        .get_if_modified(&url, &validators)
+        .await
+        .unwrap();
+    assert!(unchanged.is_none());
+}
+
+#[tokio::test]
+async fn locked_file_is_downloaded_once() {
+    let server = MockServer::start().await;
+    let dir = TempDir::new().unwrap();
+
+    Mock::given(method("GET"))
+        .and(path("/file"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(DATA))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let downloader = Arc::new(downloader());
+    let url = format!("{}/file", server.uri());
+    let file = dir.path().join("file");
+
+    let tasks = (0..4)
+        .map(|_| {
+            let (downloader, url, file) = (downloader.clone(), url.clone(), file.clone());
+            tokio::spawn(async move {
+                let _lock = lock_file(&file).await;
+                if !file.exists() {
+                    downloader.download(&url, &file, &expected()).await.unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(std::fs::read(&file).unwrap(), DATA);
+}
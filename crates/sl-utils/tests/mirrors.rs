@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use sl_utils::utils::{
+    download::Downloader,
+    mirrors::{MirrorRule, Mirrors},
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn rule(host: &str, mirrors: &[&str], fallback_to_origin: bool) -> MirrorRule {
+    MirrorRule {
+        host: host.to_string(),
+        mirrors: mirrors.iter().map(|mirror| mirror.to_string()).collect(),
+        fallback_to_origin,
+    }
+}
+
+#[test]
+fn candidates_keep_the_path() {
+    let mirrors = Mirrors::default()
+        .with_rule(rule(
+            "libraries.minecraft.net",
+            &[
+                "http://localhost:8080/maven/",
+                "https://mirror.example.com/maven",
+            ],
+            true,
+        ))
+        .with_rule(rule(
+            "piston-meta.mojang.com",
+            &["https://mirror.example.com"],
+            false,
+        ));
+
+    assert_eq!(
+        mirrors.candidates("https://libraries.minecraft.net/org/lwjgl/lwjgl.jar"),
+        vec![
+            "http://localhost:8080/maven/org/lwjgl/lwjgl.jar",
+            "https://mirror.example.com/maven/org/lwjgl/lwjgl.jar",
+            "https://libraries.minecraft.net/org/lwjgl/lwjgl.jar",
+        ]
+    );
+    assert_eq!(
+        mirrors.candidates("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json?x=1"),
+        vec!["https://mirror.example.com/mc/game/version_manifest_v2.json?x=1"]
+    );
+    assert_eq!(
+        mirrors.candidates("https://api.modrinth.com/v2/project/sodium/version"),
+        vec!["https://api.modrinth.com/v2/project/sodium/version"]
+    );
+}
+
+#[test]
+fn rules_from_json() {
+    let mirrors: Mirrors = serde_json::from_str(
+        r#"{ "rules": [{ "host": "meta.fabricmc.net", "mirrors": ["https://mirror.example.com/fabric-meta"] }] }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        mirrors.rules,
+        vec![rule(
+            "meta.fabricmc.net",
+            &["https://mirror.example.com/fabric-meta"],
+            true
+        )]
+    );
+}
+
+#[tokio::test]
+async fn falls_back_to_next_mirror() {
+    let broken = MockServer::start().await;
+    let mirror = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/assets/ab/abcdef"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&broken)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/assets/ab/abcdef"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"object".as_slice()))
+        .expect(1)
+        .mount(&mirror)
+        .await;
+
+    let mirrors = Mirrors::default().with_rule(rule(
+        "resources.download.minecraft.net",
+        &[
+            &format!("{}/assets", broken.uri()),
+            &format!("{}/assets", mirror.uri()),
+        ],
+        false,
+    ));
+    let downloader = Downloader::new(Client::new(), 4)
+        .backoff(Duration::from_millis(1))
+        .mirrors(mirrors);
+
+    let data = downloader
+        .get_bytes("https://resources.download.minecraft.net/ab/abcdef")
+        .await
+        .unwrap();
+    assert_eq!(&data[..], b"object");
+}
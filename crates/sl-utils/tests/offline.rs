@@ -0,0 +1,21 @@
+use sl_utils::utils::{download::Downloader, errors::DownloadError, http};
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+// In its own test binary, as offline mode applies to the whole process
+#[tokio::test]
+async fn offline_mode_never_reaches_the_network() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    http::set_offline(true);
+
+    let url = format!("{}/file", server.uri());
+    let err = Downloader::global().get_bytes(&url).await.unwrap_err();
+    assert!(matches!(&err, DownloadError::Offline(offline_url) if *offline_url == url));
+    assert!(!err.is_transient());
+}
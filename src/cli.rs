@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -5,6 +7,9 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Stay off the network, using only what was already downloaded
+    #[arg(long, global = true)]
+    pub offline: bool,
 }
 
 #[derive(Subcommand)]
@@ -29,15 +34,56 @@ pub enum Commands {
     Launch {
         #[arg(required = true)]
         name: String,
+        /// Username or UUID of the account to launch with, instead of the active one
+        #[arg(long)]
+        account: Option<String>,
+        /// Log in through the browser instead of a device code if the account has to log in again
+        #[arg(long)]
+        browser: bool,
+        /// Open a singleplayer world straight away, by the name of its folder in `saves`
+        #[arg(long, group = "quick_play")]
+        world: Option<String>,
+        /// Join a server straight away, as `host` or `host:port`
+        #[arg(long, group = "quick_play")]
+        server: Option<String>,
+        /// Join a realm straight away, by its id
+        #[arg(long, group = "quick_play")]
+        realm: Option<String>,
+    },
+    /// Show or change how a Minecraft instance starts
+    Settings {
         #[arg(required = true)]
-        username: String,
+        name: String,
+        /// Width of the game window
+        #[arg(long)]
+        width: Option<u32>,
+        /// Height of the game window
+        #[arg(long)]
+        height: Option<u32>,
+        /// Let the game pick the window size again
+        #[arg(long, conflicts_with_all = ["width", "height"])]
+        default_size: bool,
+        #[arg(long)]
+        fullscreen: Option<bool>,
+        /// Play the demo, for accounts that don't own the game
+        #[arg(long)]
+        demo: Option<bool>,
+        /// Only `false`, to stop maximizing a window the app maximized
+        #[arg(long)]
+        maximized: Option<bool>,
     },
-    /// List all installed Minecraft instances
-    List,
-    LaunchPremium {
+    /// Check the size and SHA-1 of every file a Minecraft instance needs
+    Verify {
         #[arg(required = true)]
         name: String,
     },
+    /// Download again the missing and corrupted files of a Minecraft instance
+    Repair {
+        #[arg(required = true)]
+        name: String,
+    },
+    /// List all installed Minecraft instances
+    List,
     AddMod {
         #[arg(required = true)]
         name: String,
@@ -47,5 +93,110 @@ pub enum Commands {
     RemoveInstallation {
         #[arg(required = true)]
         name: String
-    }
+    },
+    /// Store the password of the proxy in the secret store, read from stdin.
+    /// An empty one removes it
+    SetProxyPassword,
+    /// Manage Microsoft and offline accounts
+    Account {
+        #[command(subcommand)]
+        command: AccountCommands,
+    },
+    /// Manage the skin and cape of a Microsoft account
+    Skin {
+        #[command(subcommand)]
+        command: SkinCommands,
+        /// Username or UUID of the account, instead of the active one
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Check name availability and rename a Microsoft account
+    Name {
+        #[command(subcommand)]
+        command: NameCommands,
+        /// Username or UUID of the account, instead of the active one
+        #[arg(long)]
+        account: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AccountCommands {
+    /// List all saved accounts
+    List,
+    /// Log in with a Microsoft account
+    AddMicrosoft {
+        /// Log in through the browser instead of entering a device code
+        #[arg(long)]
+        browser: bool,
+    },
+    /// Log in with an account on a Yggdrasil (authlib-injector) server.
+    /// The password is read from the SYNTHLAUNCHER_PASSWORD environment variable, or from stdin
+    AddYggdrasil {
+        /// The server's homepage or Yggdrasil API URL
+        #[arg(required = true)]
+        server: String,
+        /// Username or email on that server
+        #[arg(required = true)]
+        username: String,
+    },
+    /// Add an offline account
+    AddOffline {
+        #[arg(required = true)]
+        username: String,
+    },
+    /// Remove a saved account
+    Remove {
+        #[arg(required = true)]
+        name: String,
+    },
+    /// Make an account the active one
+    Switch {
+        #[arg(required = true)]
+        name: String,
+    },
+    /// Make an instance always launch with an account, or clear the override
+    SetInstance {
+        #[arg(required = true)]
+        instance_name: String,
+        /// Account to use, omit to go back to the active account
+        account: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SkinCommands {
+    /// Show the current skin and the owned capes
+    Show,
+    /// Upload a 64x64 or 64x32 PNG as the new skin
+    Upload {
+        #[arg(required = true)]
+        path: PathBuf,
+        /// Use the slim (Alex) arms instead of the classic (Steve) ones
+        #[arg(long)]
+        slim: bool,
+    },
+    /// Go back to the default skin
+    Reset,
+    /// Show an owned cape, by id or name
+    Cape {
+        /// Cape to show, omit to hide the cape
+        cape: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum NameCommands {
+    /// Check whether a name is available
+    Check {
+        #[arg(required = true)]
+        name: String,
+    },
+    /// Show whether the name can be changed now
+    Status,
+    /// Change the player name
+    Change {
+        #[arg(required = true)]
+        name: String,
+    },
 }
@@ -1,30 +1,149 @@
+use std::{env, sync::Mutex};
+
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{AccountCommands, Cli, Commands, NameCommands, SkinCommands};
 use discord_rpc_client::Client;
+use indicatif::{ProgressBar, ProgressStyle};
 use sl_core::{
-    auth::{AuthFlow, PlayerProfile}, config::{config::Config, init_launcher_dir}, installations::{Installation, Installations}
+    auth::{
+        accounts::{Account, Accounts, MicrosoftAccount, OfflineAccount},
+        names::NameAvailability,
+        secrets,
+        skins::SkinVariant,
+        yggdrasil::YggdrasilAccount,
+        AuthServiceError, CancellationToken, LoginEvent, LoginMethod, MICROSOFT_CLIENT_ID,
+    },
+    config::{config::set_proxy_password, init_launcher_dir},
+    installations::{Installation, Installations},
+    json::client::VerifyReport,
+    launch::{LaunchOptions, QuickPlay},
+    progress::{InstallEvent, ProgressReporter},
 };
 use sl_mod_manager::modrinth::install_modrinth_file;
-use sl_utils::utils::errors::BackendError;
+use sl_utils::utils::{errors::BackendError, http};
 
 mod cli;
 
+/// Environment variable holding the password of a Yggdrasil account, so it isn't passed as an argument.
+const PASSWORD_ENV: &str = "SYNTHLAUNCHER_PASSWORD";
+
+fn print_login_event(event: LoginEvent) {
+    match event {
+        LoginEvent::Code(code_res) => println!(
+            "Open this link in your browser {} and enter the following code: {}\nWaiting authentication...",
+            code_res.verification_uri, code_res.user_code
+        ),
+        LoginEvent::OpenBrowser { url } => {
+            println!("Log in through your browser, if it didn't open visit:\n{url}\nWaiting authentication...");
+            let _ = open::that(&url);
+        }
+        LoginEvent::Waiting { .. } => {}
+    }
+}
+
+/// Draws a progress bar for every install phase, counting bytes when their total is known.
+fn progress_bar() -> ProgressReporter {
+    let bar: Mutex<Option<(ProgressBar, bool)>> = Mutex::new(None);
+
+    ProgressReporter::new(move |event| {
+        let mut bar = bar.lock().unwrap();
+
+        match event {
+            InstallEvent::PhaseStarted { phase, progress } => {
+                let by_bytes = progress.bytes_total > 0;
+                let (len, template) = if by_bytes {
+                    (progress.bytes_total, "{msg:>16} [{bar:40}] {bytes}/{total_bytes}")
+                } else {
+                    (progress.files_total as u64, "{msg:>16} [{bar:40}] {pos}/{len}")
+                };
+                let style = ProgressStyle::with_template(template)
+                    .unwrap()
+                    .progress_chars("=> ");
+
+                *bar = Some((
+                    ProgressBar::new(len).with_style(style).with_message(phase.to_string()),
+                    by_bytes,
+                ));
+            }
+            InstallEvent::FileDone { progress, .. } => {
+                if let Some((bar, by_bytes)) = bar.as_ref() {
+                    bar.set_position(if *by_bytes {
+                        progress.bytes_done
+                    } else {
+                        progress.files_done as u64
+                    });
+                }
+            }
+            InstallEvent::PhaseFinished { .. } => {
+                if let Some((bar, _)) = bar.take() {
+                    bar.finish();
+                }
+            }
+        }
+    })
+}
+
+fn print_report(report: &VerifyReport, missing: &str, corrupted: &str) {
+    for path in &report.missing {
+        println!("{missing}: {}", path.display());
+    }
+    for path in &report.corrupted {
+        println!("{corrupted}: {}", path.display());
+    }
+
+    if report.is_ok() {
+        println!("All files are valid");
+    }
+}
+
+fn login_method(browser: bool) -> LoginMethod {
+    if browser {
+        LoginMethod::Browser
+    } else {
+        LoginMethod::DeviceCode
+    }
+}
+
+/// Prints auth errors as is, their messages tell the user what to do.
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<(), BackendError> {
-    init_launcher_dir().await.unwrap();
-
     let cli = Cli::parse();
 
+    http::set_offline(cli.offline);
+    init_launcher_dir().await.unwrap();
+
     match cli.command {
         Commands::Install { name, version } => {
-            let mut instance = Installation::new(&name, &version).unwrap();
-            instance.install().await.unwrap();
+            let mut instance = Installation::new(&name, &version)?;
+            instance.install(&progress_bar()).await.unwrap();
         }
-        Commands::Launch { name, username } => {
-            let mut config = Config::read_global().unwrap();
-            config
-                .update_config_field("auth_player_name", username.as_str())
-                .unwrap();
+        Commands::Launch {
+            name,
+            account,
+            browser,
+            world,
+            server,
+            realm,
+        } => {
+            let instance = Installations::find(&name).unwrap();
+            let account = account.or_else(|| instance.account());
+            let account = or_exit(
+                Accounts::resolve(
+                    MICROSOFT_CLIENT_ID,
+                    account.as_deref(),
+                    login_method(browser),
+                    &CancellationToken::new(),
+                    print_login_event,
+                )
+                .await,
+            );
 
             let rpc_handle = tokio::spawn(async {
                 let mut drpc = Client::new(1369620733453664287);
@@ -46,22 +165,81 @@ async fn main() -> Result<(), BackendError> {
                 } 
             });
 
-            let instance = Installations::find(&name).unwrap();
-            instance.execute(None).unwrap();
-            
+            let quick_play = world
+                .map(QuickPlay::Singleplayer)
+                .or(server.map(QuickPlay::Multiplayer))
+                .or(realm.map(QuickPlay::Realms));
+            let options = LaunchOptions {
+                quick_play,
+                ..Default::default()
+            };
+
+            or_exit(instance.execute(&account, &options).await);
+
             rpc_handle.abort();
         }
         Commands::InstallFabric {
             instance_name,
             loader_version,
         } => {
-            let mut instance =
-                Installations::find(&instance_name).expect("failed to find instance");
+            let mut instance = or_exit(Installations::find(&instance_name));
+            let progress = progress_bar();
             instance
-                .install_fabric(&loader_version)
+                .install_fabric(&loader_version, &progress)
                 .await
                 .expect("failed to install fabric");
-            instance.install().await.unwrap();
+            instance.install(&progress).await.unwrap();
+        }
+        Commands::Settings {
+            name,
+            width,
+            height,
+            default_size,
+            fullscreen,
+            demo,
+            maximized,
+        } => {
+            // Maximizing needs the size of the screen, which only the app knows
+            if maximized == Some(true) {
+                eprintln!("Can't maximize from the command line, set --width and --height instead");
+                std::process::exit(1);
+            }
+
+            let mut instance = Installations::find(&name)?;
+            let mut settings = instance.launch_settings();
+
+            if default_size {
+                settings.width = None;
+                settings.height = None;
+            }
+            settings.width = width.or(settings.width);
+            settings.height = height.or(settings.height);
+            settings.fullscreen = fullscreen.unwrap_or(settings.fullscreen);
+            settings.demo = demo.unwrap_or(settings.demo);
+            settings.maximized = maximized.unwrap_or(settings.maximized);
+            instance.set_launch_settings(&settings)?;
+
+            let size =
+                |size: Option<u32>| size.map_or("default".to_owned(), |size| size.to_string());
+            println!("width: {}", size(settings.width));
+            println!("height: {}", size(settings.height));
+            println!("fullscreen: {}", settings.fullscreen);
+            println!("demo: {}", settings.demo);
+            println!("maximized: {}", settings.maximized);
+        }
+        Commands::Verify { name } => {
+            let mut instance = Installations::find(&name)?;
+            let report = instance.verify().await?;
+            print_report(&report, "missing", "corrupted");
+
+            if !report.is_ok() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Repair { name } => {
+            let mut instance = Installations::find(&name)?;
+            let report = instance.repair().await?;
+            print_report(&report, "downloaded", "replaced");
         }
         Commands::List => {
             let installations = Installations::load()?;
@@ -71,23 +249,6 @@ async fn main() -> Result<(), BackendError> {
                 count += 1;
             }
         },
-        Commands::LaunchPremium { name } => {
-            let mut auth = AuthFlow::new("74909cec-49b6-4fee-aa60-1b2a57ef72e1");
-            let code_res = auth.request_code().await.unwrap();
-        
-            println!(
-                "Open this link in your browser {} and enter the following code: {}\nWaiting authentication...",
-                code_res.verification_uri, code_res.user_code
-            );
-            
-            auth.wait_for_login().await.unwrap();
-            auth.login_in_xbox_live().await.unwrap();
-            let minecraft = auth.login_in_minecraft().await.unwrap();
-            let profile = PlayerProfile::new(minecraft.access_token.clone()).await.unwrap();
-
-            let instance = Installations::find(&name).unwrap();
-            instance.execute(Some(&profile)).unwrap();
-        },
         Commands::AddMod { name, id } => {
             let installation = Installations::find(&name).unwrap();
             let dest = installation.dir_path().join("mods");
@@ -98,6 +259,180 @@ async fn main() -> Result<(), BackendError> {
         Commands::RemoveInstallation { name } => {
             Installations::remove(&name)?;
         }
+        Commands::SetProxyPassword => {
+            println!("Proxy password:");
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password).unwrap();
+            let password = password.trim_end_matches(['\r', '\n']);
+
+            let secrets = or_exit(secrets::default_backend());
+            or_exit(set_proxy_password(
+                &secrets,
+                Some(password).filter(|password| !password.is_empty()),
+            ));
+        }
+        Commands::Account { command } => {
+            let mut accounts = or_exit(Accounts::load());
+
+            match command {
+                AccountCommands::List => {
+                    for account in &accounts.accounts {
+                        let kind = match account {
+                            Account::Microsoft(_) => "microsoft",
+                            Account::Offline(_) => "offline",
+                            Account::Yggdrasil(_) => "yggdrasil",
+                        };
+                        let marker = if accounts.active.as_deref() == Some(account.id()) {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        println!("{} {} ({})", marker, account.username(), kind);
+                    }
+                }
+                AccountCommands::AddMicrosoft { browser } => {
+                    let account = or_exit(
+                        MicrosoftAccount::login(
+                            MICROSOFT_CLIENT_ID,
+                            login_method(browser),
+                            &CancellationToken::new(),
+                            print_login_event,
+                        )
+                        .await,
+                    );
+                    println!("Logged in as {}", account.username);
+                    accounts.add(Account::Microsoft(account));
+                }
+                AccountCommands::AddYggdrasil { server, username } => {
+                    let password = env::var(PASSWORD_ENV).unwrap_or_else(|_| {
+                        println!("Password:");
+                        let mut password = String::new();
+                        std::io::stdin().read_line(&mut password).unwrap();
+                        password.trim_end_matches(['\r', '\n']).to_string()
+                    });
+
+                    let account =
+                        or_exit(YggdrasilAccount::login(&server, &username, &password).await);
+                    println!("Logged in as {}", account.username);
+                    accounts.add(Account::Yggdrasil(account));
+                }
+                AccountCommands::AddOffline { username } => {
+                    accounts.add(Account::Offline(or_exit(OfflineAccount::new(&username))));
+                }
+                AccountCommands::Remove { name } => {
+                    if let Account::Yggdrasil(account) = or_exit(accounts.remove(&name)) {
+                        // The account is gone either way, the server may just be unreachable
+                        let _ = account.logout().await;
+                    }
+                }
+                AccountCommands::Switch { name } => {
+                    let account = or_exit(accounts.switch(&name));
+                    println!("Switched to {}", account.username());
+                }
+                AccountCommands::SetInstance {
+                    instance_name,
+                    account,
+                } => {
+                    if let Some(ref account) = account {
+                        or_exit(
+                            accounts
+                                .find(account)
+                                .ok_or_else(|| AuthServiceError::AccountNotFound(account.clone())),
+                        );
+                    }
+
+                    let mut instance = or_exit(Installations::find(&instance_name));
+                    instance.set_account(account.as_deref())?;
+                }
+            }
+
+            or_exit(accounts.overwrite());
+        }
+        Commands::Skin { command, account } => {
+            let account = or_exit(
+                Accounts::resolve(
+                    MICROSOFT_CLIENT_ID,
+                    account.as_deref(),
+                    LoginMethod::DeviceCode,
+                    &CancellationToken::new(),
+                    print_login_event,
+                )
+                .await,
+            );
+            let mut profile = or_exit(account.profile().await);
+
+            match command {
+                SkinCommands::Show => {}
+                SkinCommands::Upload { path, slim } => {
+                    let variant = if slim {
+                        SkinVariant::Slim
+                    } else {
+                        SkinVariant::Classic
+                    };
+                    or_exit(profile.upload_skin(&path, variant).await);
+                }
+                SkinCommands::Reset => or_exit(profile.reset_skin().await),
+                SkinCommands::Cape { cape } => or_exit(profile.set_cape(cape.as_deref()).await),
+            }
+
+            match profile.active_skin() {
+                Some(skin) => println!("Skin: {} ({:?})", skin.url, skin.variant),
+                None => println!("Skin: default"),
+            }
+            for cape in &profile.capes {
+                let marker = if Some(cape) == profile.active_cape() {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {} ({})", marker, cape.alias, cape.id);
+            }
+        }
+        Commands::Name { command, account } => {
+            let account = or_exit(
+                Accounts::resolve(
+                    MICROSOFT_CLIENT_ID,
+                    account.as_deref(),
+                    LoginMethod::DeviceCode,
+                    &CancellationToken::new(),
+                    print_login_event,
+                )
+                .await,
+            );
+            let mut profile = or_exit(account.profile().await);
+
+            match command {
+                NameCommands::Check { name } => {
+                    match or_exit(profile.name_availability(&name).await) {
+                        NameAvailability::Available => println!("{name} is available"),
+                        NameAvailability::Duplicate => println!("{name} is already taken"),
+                        NameAvailability::NotAllowed => println!("{name} is not allowed"),
+                    }
+                }
+                NameCommands::Status => {
+                    let status = or_exit(profile.name_change_status().await);
+                    if status.name_change_allowed {
+                        println!("The name can be changed");
+                    } else {
+                        println!(
+                            "The name can't be changed yet, it was last changed at {}",
+                            status.changed_at.as_deref().unwrap_or("unknown")
+                        );
+                    }
+                }
+                NameCommands::Change { name } => {
+                    or_exit(profile.change_name(&name).await);
+
+                    if let Account::Microsoft(mut microsoft) = account {
+                        microsoft.username = profile.username.clone();
+                        let mut accounts = or_exit(Accounts::load());
+                        accounts.add(Account::Microsoft(microsoft));
+                        or_exit(accounts.overwrite());
+                    }
+                    println!("Renamed to {}", profile.username);
+                }
+            }
+        }
     }
 
     Ok(())